@@ -0,0 +1,260 @@
+use luca::{solve_typed, Error, LineState, ResType};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read, Write};
+use std::rc::Rc;
+
+// Command line mode, to use Luca without its window
+
+const USAGE: &str = "Usage: luca [--eval EXPRESSION | --repl | --stdin [--ignore-errors]] [--json]";
+
+/// The arguments of the command line mode, the other ones are for the window, like a file to open
+const FLAGS: [&str; 6] = ["--eval", "--repl", "--stdin", "--ignore-errors", "--json", "--help"];
+
+/// What the command line asks for
+enum Mode {
+    Eval(String),
+    Repl,
+    /// Solve the lines read from the standard input
+    Stdin
+}
+
+/// Run the command line mode asked by the `args`, without the program name,
+/// and return the exit code. None if there are none of the `FLAGS`, to open the window.
+pub fn run(args: &[String]) -> Option<i32> {
+    if !args.iter().any(|arg| FLAGS.contains(&arg.as_str())) {
+        return None;
+    }
+
+    let mut mode = None;
+    // the exit code stays 0 when a line fails
+    let mut ignore_errors = false;
+    // print the results as a JSON array, for other programs
+    let mut json = false;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--eval" => mode = args.next().map(|expression| Mode::Eval(expression.clone())),
+            "--repl" => mode = Some(Mode::Repl),
+            "--stdin" => mode = Some(Mode::Stdin),
+            "--ignore-errors" => ignore_errors = true,
+            "--json" => json = true,
+            "--help" => {
+                println!("{}", USAGE);
+                return Some(0);
+            },
+            _ => {
+                mode = None;
+                break;
+            }
+        }
+    }
+
+    let code = match mode {
+        Some(Mode::Eval(expression)) => eval(&expression, json),
+        Some(Mode::Repl) => {
+            repl();
+            0
+        },
+        Some(Mode::Stdin) => {
+            let mut text = String::new();
+            if let Err(error) = io::stdin().read_to_string(&mut text) {
+                eprintln!("{}", error);
+                return Some(1);
+            }
+            solve_input(&text, json)
+        },
+        None => {
+            eprintln!("{}", USAGE);
+            return Some(2);
+        }
+    };
+
+    Some(if ignore_errors { 0 } else { code })
+}
+
+/// Print the result of the `expression`, or its error
+fn eval(expression: &str, json: bool) -> i32 {
+    let variables = Rc::new(RefCell::new(HashMap::new()));
+    let result = solve_typed(expression.to_string(), variables, &HashMap::new());
+
+    if json {
+        println!("{}", to_json(&[(expression, &result)]));
+        return i32::from(result.is_err());
+    }
+
+    match result {
+        Ok(result) => {
+            println!("{}", result);
+            0
+        },
+        Err(error) => {
+            eprintln!("{}", error);
+            1
+        }
+    }
+}
+
+/// Print the result of each statement of the `text`, and the errors with their line number
+fn solve_input(text: &str, json: bool) -> i32 {
+    let mut state = LineState::default();
+    let rates = HashMap::new();
+
+    // the blank lines, the comments and the lines continued on the next one have no result.
+    // A continued statement is given with all its lines.
+    let mut results: Vec<(usize, String, Result<ResType, Error>)> = text.lines()
+        .enumerate()
+        .filter_map(|(number, line)| {
+            let result = state.solve_typed(number, line, &rates)?;
+            Some((number, state.statement().to_string(), result.map(|solution| solution.value)))
+        })
+        .collect();
+    if let Some((number, result)) = state.finish(&rates) {
+        results.push((number, state.statement().to_string(), result.map(|solution| solution.value)));
+    }
+
+    let lines: Vec<(usize, &str, &Result<ResType, Error>)> = results.iter()
+        .map(|(number, statement, result)| (*number, statement.as_str(), result))
+        .collect();
+
+    if json {
+        let lines: Vec<(&str, &Result<ResType, Error>)> = lines.iter().map(|&(_, line, result)| (line, result)).collect();
+        println!("{}", to_json(&lines));
+    } else {
+        for (number, _, result) in &lines {
+            match result {
+                Ok(value) => println!("{}", value),
+                Err(error) => eprintln!("line {}: {}", number + 1, error)
+            }
+        }
+    }
+
+    i32::from(lines.iter().any(|(_, _, result)| result.is_err()))
+}
+
+/// A JSON array with an object for each line and its result:
+/// `{"input": "10€", "value": 10, "type": "Money", "currency": "EUR", "error": null}`
+fn to_json(lines: &[(&str, &Result<ResType, Error>)]) -> String {
+    let objects: Vec<String> = lines.iter()
+        .map(|(input, result)| {
+            let mut fields = vec![format!("\"input\": {}", json_string(input))];
+
+            match result {
+                Ok(value) => {
+                    let (kind, json_value) = json_value(value);
+                    fields.push(format!("\"value\": {}", json_value));
+                    fields.push(format!("\"type\": \"{}\"", kind));
+                    match value {
+                        ResType::Money(_, currency) => fields.push(format!("\"currency\": \"{}\"", currency.code())),
+                        ResType::Quantity(_, unit) => fields.push(format!("\"unit\": {}", json_string(&unit.to_string()))),
+                        _ => {}
+                    }
+                    fields.push(String::from("\"error\": null"));
+                },
+                Err(error) => {
+                    fields.push(String::from("\"value\": null"));
+                    fields.push(String::from("\"type\": null"));
+                    fields.push(format!("\"error\": {}", json_string(&error.to_string())));
+                }
+            }
+
+            format!("  {{{}}}", fields.join(", "))
+        })
+        .collect();
+
+    if objects.is_empty() {
+        String::from("[]")
+    } else {
+        format!("[\n{}\n]", objects.join(",\n"))
+    }
+}
+
+/// The name of the type of the `value`, and the value in JSON.
+/// The numbers that JSON can't hold exactly, like the fractions, are strings.
+fn json_value(value: &ResType) -> (&'static str, String) {
+    let number = |val: f64| if val.is_finite() { val.to_string() } else { String::from("null") };
+
+    match value {
+        ResType::Int(val) => ("Int", val.to_string()),
+        ResType::Float(val) => ("Float", number(*val)),
+        ResType::Money(val, _) => ("Money", number(*val)),
+        ResType::Bool(val) => ("Bool", val.to_string()),
+        ResType::Rational(_, _) => ("Rational", json_string(&value.to_string())),
+        ResType::Date(_) => ("Date", json_string(&value.to_string())),
+        // in seconds
+        ResType::Duration(val) => ("Duration", number(*val)),
+        ResType::Quantity(val, _) => ("Quantity", number(*val)),
+        ResType::Complex(_, _) => ("Complex", json_string(&value.to_string())),
+        // an object with the value of each variable: {"a": 1, "b": 2}
+        ResType::Variables(variables) => {
+            let members: Vec<String> = variables.iter()
+                .map(|(name, value)| format!("{}: {}", json_string(name), json_value(value).1))
+                .collect();
+            ("Variables", format!("{{{}}}", members.join(", ")))
+        },
+        #[cfg(feature = "bigint")]
+        ResType::BigInt(val) => ("BigInt", json_string(&val.to_string()))
+    }
+}
+
+/// The `text` as a JSON string, with its quotes
+fn json_string(text: &str) -> String {
+    let mut json = String::from("\"");
+    for char in text.chars() {
+        match char {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            char if char.is_control() => json.push_str(&format!("\\u{:04x}", char as u32)),
+            char => json.push(char)
+        }
+    }
+    json.push('"');
+    json
+}
+
+/// Solve the lines typed by the user, like the lines of the window: with `ans`, `line1`...
+fn repl() {
+    let mut state = LineState::default();
+    let rates = HashMap::new();
+    let mut lines = io::stdin().lock().lines();
+
+    for number in 0.. {
+        // show the interactive prompt
+        print!("luca> ");
+        _ = io::stdout().flush();
+
+        let line = match lines.next() {
+            Some(Ok(line)) if line != "exit" => line,
+            _ => break
+        };
+
+        match state.solve(number, &line, &rates) {
+            Some(Ok(solution)) => println!("{}", solution.value),
+            Some(Err(message)) => println!("{}", message),
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_window_arguments() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<String>>();
+
+        // the arguments of the window are left to the application
+        assert_eq!(run(&[]), None);
+        assert_eq!(run(&args(&["notes.txt"])), None);
+        assert_eq!(run(&args(&["--gapplication-service"])), None);
+
+        assert_eq!(run(&args(&["--help"])), Some(0));
+        assert_eq!(run(&args(&["--json"])), Some(2));
+        assert_eq!(run(&args(&["--eval"])), Some(2));
+    }
+}
@@ -0,0 +1,82 @@
+use crate::input_pane::LineResult;
+
+// Export of the document with its results, to use them in other applications
+
+/// A CSV row of the `fields`. The fields with a comma, a quote or a line break are quoted.
+pub fn csv_row(fields: &[&str]) -> String {
+    let fields: Vec<String> = fields.iter()
+        .map(|field| {
+            if field.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect();
+
+    fields.join(",")
+}
+
+/// Each line of the `text` with its result, in two CSV columns
+pub fn to_csv(text: &str, results: &[LineResult]) -> String {
+    let mut csv = String::new();
+
+    for (number, line) in text.lines().enumerate() {
+        let result = results.get(number).map_or("", LineResult::text);
+        csv.push_str(&csv_row(&[line, result]));
+        csv.push('\n');
+    }
+
+    csv
+}
+
+/// A Markdown table of the lines of the `text` with a result, the blank lines and the comments are left out
+pub fn to_markdown(text: &str, results: &[LineResult]) -> String {
+    // a pipe would end the cell
+    let escape = |cell: &str| cell.replace('|', "\\|");
+    let mut table = String::from("| Expression | Result |\n| --- | --- |\n");
+
+    for (line, result) in text.lines().zip(results) {
+        if *result == LineResult::Empty {
+            continue;
+        }
+        table.push_str(&format!("| {} | {} |\n", escape(line.trim()), escape(result.text())));
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv() {
+        assert_eq!(csv_row(&["1 + 1", "2"]), "1 + 1,2");
+        assert_eq!(csv_row(&["10€ + 5€", "15.00 €"]), "10€ + 5€,15.00 €");
+        assert_eq!(csv_row(&["1000 * 2", "2,000"]), "1000 * 2,\"2,000\"");
+        assert_eq!(csv_row(&["say \"hi\"", ""]), "\"say \"\"hi\"\"\",");
+
+        let results = vec![LineResult::Value(String::from("4")), LineResult::Empty, LineResult::Error(String::from("Overflow"))];
+        assert_eq!(to_csv("2 * 2\n# note\n10!!!", &results), "2 * 2,4\n# note,\n10!!!,Overflow\n");
+    }
+
+    #[test]
+    fn test_markdown() {
+        let results = vec![
+            LineResult::Value(String::from("x = 12")),
+            LineResult::Empty,
+            LineResult::Value(String::from("15")),
+            LineResult::Error(String::from("Division by zero"))
+        ];
+        let text = "x = 12\n\n12 | 3\nx / 0";
+
+        assert_eq!(to_markdown(text, &results), concat!(
+            "| Expression | Result |\n",
+            "| --- | --- |\n",
+            "| x = 12 | x = 12 |\n",
+            "| 12 \\| 3 | 15 |\n",
+            "| x / 0 | Division by zero |\n"
+        ));
+    }
+}
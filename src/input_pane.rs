@@ -1,31 +1,78 @@
-use gtk::prelude::{WidgetExt, TextBufferExt, TextViewExt};
+use gtk::prelude::{AdjustmentExt, DrawingAreaExtManual, ObjectExt, PopoverExt, ScrollableExt, WidgetExt, TextBufferExt, TextViewExt};
+use gtk::{glib, glib::clone};
 use relm4::{gtk, ComponentParts, ComponentSender, SimpleComponent};
 
-use crate::interpreter::{solve, ResType};
+use luca::{is_line_variable, AngleMode, Currency, FormatOptions, LineState, ResType, StatementKind};
 use std::collections::HashMap;
 use std::cell::RefCell;
 use std::rc::Rc;
 
 // Input component
 
+/// How long the user must stop typing before the document is solved again
+const RECOMPUTE_DELAY: std::time::Duration = std::time::Duration::from_millis(150);
+
 pub struct LucaInput {
-    text_buffer: gtk::TextBuffer
+    text_buffer: gtk::TextBuffer,
+    changed_handler: glib::SignalHandlerId,
+    /// The recomputation waiting for the user to stop typing
+    pending: Rc<RefCell<Option<glib::SourceId>>>,
+    text_view: gtk::TextView,
+    line_numbers: gtk::DrawingArea,
+    /// The variables assigned in the document, suggested while typing
+    names: Rc<RefCell<Vec<String>>>,
+    completion: gtk::Popover,
+    completion_label: gtk::Label,
+    /// The lines solved by the last recomputation, the next one starts from the first edited line
+    solved: Vec<SolvedLine>,
+    // kept between the recomputations, so we don't allocate them on every keystroke
+    rates: HashMap<(Currency, Currency), f64>,
+    format: FormatOptions,
+    /// The unit of the angles of the trigonometric functions
+    angle_mode: AngleMode
+}
+
+#[derive(Debug)]
+pub enum InputMsg {
+    Recompute,
+    /// Replace the whole document, like when a file is opened
+    Load(String),
+    /// Show the number of the lines on the left of the input
+    ShowLineNumbers(bool),
+    /// Complete the variable name before the cursor
+    Complete,
+    /// Insert the result of the previous lines at the cursor
+    InsertPreviousResult,
+    /// Comment or uncomment the line of the cursor, or the selected lines
+    ToggleComment,
+    /// Solve the trigonometric functions in degrees or in radians
+    SetAngleMode(AngleMode),
+    /// Remove the whole document and the variables it assigned
+    Clear
 }
 
 #[derive(Debug)]
 pub enum MsgInput {
-    TextChanged(String)
+    TextChanged(Vec<LineResult>),
+    /// The results of a document that has just been loaded
+    Loaded(Vec<LineResult>),
+    /// The results changed without any edit, like when the angle mode is changed
+    Recomputed(Vec<LineResult>),
+    /// The document has been cleared, there's no result anymore
+    Cleared
 }
 
 #[relm4::component(pub)]
 impl SimpleComponent for LucaInput {
     type Init = String;
-    type Input = ();
+    type Input = InputMsg;
     type Output = MsgInput;
 
     view! {
         gtk::TextView {
             set_margin_start: 20,
+            // a line is a single row, like its result: the long lines scroll horizontally
+            set_wrap_mode: gtk::WrapMode::None,
             set_buffer: Some(&model.text_buffer)
         },
     }
@@ -35,45 +82,789 @@ impl SimpleComponent for LucaInput {
         root: Self::Root,
         sender: ComponentSender<Self>,
     ) -> ComponentParts<Self> {
+        // Ctrl+Z and Ctrl+Shift+Z undo and redo the edits, with the bindings of the TextView
         let text_buffer = gtk::TextBuffer::new(None);
-        text_buffer.set_text(&text);
-
-        text_buffer.connect_changed(move |text_buffer| {
-            let start_iter = text_buffer.start_iter();
-            let end_iter = text_buffer.end_iter();
-            let text = text_buffer.text(&start_iter, &end_iter, false);
-
-            // interpret the text from the input pane
-            let mut results = String::new();
-            let variables : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
-            
-            for line in text.lines() {
-
-                if let Ok(res) = solve(line.to_string(), variables.clone()) {
-                    results.push_str(&res);
-                    results.push_str("\n");
+        text_buffer.set_enable_undo(true);
+        load_text(&text_buffer, &text);
+
+        // the fast edits are solved once, after the user pauses
+        let pending: Rc<RefCell<Option<glib::SourceId>>> = Rc::new(RefCell::new(None));
+        let changed_handler = text_buffer.connect_changed(clone!(@strong sender, @strong pending => move |_| {
+            if let Some(source) = pending.borrow_mut().take() {
+                source.remove();
+            }
+
+            let source = glib::timeout_add_local_once(RECOMPUTE_DELAY, clone!(@strong sender, @strong pending => move || {
+                pending.borrow_mut().take();
+                sender.input(InputMsg::Recompute);
+            }));
+            *pending.borrow_mut() = Some(source);
+        }));
+
+        let line_numbers = line_numbers_gutter(&root, &text_buffer);
+
+        // the partner of the parenthesis at the cursor, and the parentheses without one
+        let match_tag = gtk::TextTag::builder()
+            .name("matching-paren")
+            .background("rgba(100, 186, 255, 0.3)")
+            .build();
+        let unmatched_tag = gtk::TextTag::builder()
+            .name("unmatched-paren")
+            .foreground("#c6262e")
+            .build();
+        text_buffer.tag_table().add(&match_tag);
+        text_buffer.tag_table().add(&unmatched_tag);
+
+        text_buffer.connect_mark_set(clone!(@weak match_tag, @weak unmatched_tag => move |buffer, _, mark| {
+            if *mark == buffer.get_insert() {
+                highlight_parens(buffer, &match_tag, &unmatched_tag);
+            }
+        }));
+        text_buffer.connect_changed(clone!(@weak match_tag, @weak unmatched_tag => move |buffer| {
+            highlight_parens(buffer, &match_tag, &unmatched_tag);
+        }));
+
+        // suggestions of variable names, the focus stays in the input
+        let completion_label = gtk::Label::new(None);
+        let completion = gtk::Popover::builder()
+            .autohide(false)
+            .has_arrow(false)
+            .can_focus(false)
+            .position(gtk::PositionType::Bottom)
+            .child(&completion_label)
+            .build();
+        completion.set_parent(&root);
+
+        let names: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(Vec::new()));
+
+        let keys = gtk::EventControllerKey::new();
+        keys.connect_key_pressed(clone!(@strong sender, @strong names, @weak text_buffer, @weak completion
+            => @default-return glib::Propagation::Proceed, move |_, key, _, modifiers| {
+            match key {
+                gtk::gdk::Key::r if modifiers.contains(gtk::gdk::ModifierType::CONTROL_MASK) => {
+                    sender.input(InputMsg::InsertPreviousResult);
+                    glib::Propagation::Stop
+                },
+                gtk::gdk::Key::slash if modifiers.contains(gtk::gdk::ModifierType::CONTROL_MASK) => {
+                    sender.input(InputMsg::ToggleComment);
+                    glib::Propagation::Stop
+                },
+                gtk::gdk::Key::Tab => {
+                    let (prefix, _) = word_at_cursor(&text_buffer);
+                    if completions(&names.borrow(), &prefix).is_empty() {
+                        return glib::Propagation::Proceed;
+                    }
+                    sender.input(InputMsg::Complete);
+                    glib::Propagation::Stop
+                },
+                gtk::gdk::Key::Escape if completion.is_visible() => {
+                    completion.popdown();
+                    glib::Propagation::Stop
+                },
+                _ => glib::Propagation::Proceed
+            }
+        }));
+        root.add_controller(keys);
+
+        let model = LucaInput {
+            text_buffer,
+            changed_handler,
+            pending,
+            text_view: root.clone(),
+            line_numbers,
+            names,
+            completion,
+            completion_label,
+            solved: Vec::new(),
+            rates: HashMap::new(),
+            format: FormatOptions {
+                thousands_separator: Some(','),
+                ..Default::default()
+            },
+            angle_mode: AngleMode::Radians
+        };
+        let widgets = view_output!();
+        ComponentParts {model, widgets}
+    }
+
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
+        match msg {
+            InputMsg::Recompute => {
+                let results = self.solve_all();
+                sender.output(MsgInput::TextChanged(results)).unwrap();
+
+                // no popup for a single letter, unless Tab is pressed
+                self.suggest(2);
+            },
+            InputMsg::Load(text) => {
+                // the edits of the previous document don't need to be solved
+                if let Some(source) = self.pending.borrow_mut().take() {
+                    source.remove();
+                }
+
+                // a loaded document isn't a modification by the user
+                self.text_buffer.block_signal(&self.changed_handler);
+                load_text(&self.text_buffer, &text);
+                self.text_buffer.unblock_signal(&self.changed_handler);
+
+                let results = self.solve_all();
+                sender.output(MsgInput::Loaded(results)).unwrap();
+            },
+            InputMsg::ShowLineNumbers(show) => {
+                let gutter = if show { Some(&self.line_numbers) } else { None };
+                self.text_view.set_gutter(gtk::TextWindowType::Left, gutter);
+            },
+            InputMsg::Complete => {
+                let (prefix, _) = word_at_cursor(&self.text_buffer);
+                let common = common_prefix(&completions(&self.names.borrow(), &prefix));
+
+                if common.len() > prefix.len() {
+                    user_action(&self.text_buffer, |buffer| buffer.insert_at_cursor(&common[prefix.len()..]));
                 } else {
-                    results.push('\n');
+                    // several names start with the prefix, show them
+                    self.suggest(1);
+                }
+            },
+            InputMsg::InsertPreviousResult => {
+                let line = self.text_buffer.iter_at_mark(&self.text_buffer.get_insert()).line() as usize;
+
+                // `ans` after the previous line, without the thousands separators so it can be computed again
+                let previous = line.checked_sub(1)
+                    .and_then(|previous| self.solved.get(previous))
+                    .and_then(|solved| solved.state.variables().get("ans").map(insertable));
+
+                if let Some(previous) = previous {
+                    user_action(&self.text_buffer, |buffer| buffer.insert_at_cursor(&previous));
                 }
+            },
+            InputMsg::ToggleComment => self.toggle_comment(),
+            InputMsg::SetAngleMode(angle_mode) => {
+                // every line is solved again in the new mode
+                self.angle_mode = angle_mode;
+                self.solved.clear();
+
+                let results = self.solve_all();
+                sender.output(MsgInput::Recomputed(results)).unwrap();
+            },
+            InputMsg::Clear => {
+                if let Some(source) = self.pending.borrow_mut().take() {
+                    source.remove();
+                }
+                self.completion.popdown();
+
+                self.text_buffer.block_signal(&self.changed_handler);
+                load_text(&self.text_buffer, "");
+                self.text_buffer.unblock_signal(&self.changed_handler);
+
+                // without any line, there's no variable left
+                self.solve_all();
+                sender.output(MsgInput::Cleared).unwrap();
+            }
+        }
+    }
+}
+
+impl LucaInput {
+    /// The whole document typed by the user
+    pub fn text(&self) -> String {
+        let start_iter = self.text_buffer.start_iter();
+        let end_iter = self.text_buffer.end_iter();
+        self.text_buffer.text(&start_iter, &end_iter, false).to_string()
+    }
+
+    /// Interpret every line of the input pane, and return the results one per line
+    fn solve_all(&mut self) -> Vec<LineResult> {
+        let results = solve_lines(&self.text(), &mut self.solved, &self.rates, &self.format, self.angle_mode);
+        *self.names.borrow_mut() = variable_names(&self.solved);
+        results
+    }
+
+    /// Comment the selected lines, or uncomment them if they all are comments.
+    /// The selection, or the cursor, stays on the same text.
+    fn toggle_comment(&self) {
+        let buffer = &self.text_buffer;
+        let (selection, cursor) = (buffer.selection_bound(), buffer.get_insert());
+        let (first, last) = buffer.selection_bounds()
+            .map(|(start, end)| {
+                // a selection ending at the start of a line doesn't include it
+                let last = if end.starts_line() && end.line() > start.line() { end.line() - 1 } else { end.line() };
+                (start.line(), last)
+            })
+            .unwrap_or_else(|| {
+                let line = buffer.iter_at_mark(&cursor).line();
+                (line, line)
+            });
+
+        let mut start = buffer.iter_at_line(first).unwrap_or(buffer.start_iter());
+        let mut end = buffer.iter_at_line(last).unwrap_or(buffer.end_iter());
+        if !end.ends_line() {
+            end.forward_to_line_end();
+        }
+
+        let text = buffer.text(&start, &end, false).to_string();
+        let toggled = toggle_comment(&text);
+
+        // where the marks were, moved by the chars added or removed before them on their line
+        let old_lines: Vec<&str> = text.split('\n').collect();
+        let new_lines: Vec<&str> = toggled.split('\n').collect();
+        let position = |mark: &gtk::TextMark| {
+            let iter = buffer.iter_at_mark(mark);
+            let (line, offset) = (iter.line(), iter.line_offset());
+            if line < first || line > last {
+                return (line, offset);
             }
-            results.pop();
+            let index = (line - first) as usize;
+            let delta = new_lines[index].chars().count() as i32 - old_lines[index].chars().count() as i32;
+            (line, (offset + delta).max(0))
+        };
+        let (selection_position, cursor_position) = (position(&selection), position(&cursor));
 
-            sender.output(MsgInput::TextChanged(results.to_string())).unwrap();
+        user_action(buffer, |buffer| {
+            buffer.delete(&mut start, &mut end);
+            buffer.insert(&mut start, &toggled);
         });
 
-        let model = LucaInput {text_buffer};
-        let widgets = view_output!();
-        ComponentParts {model, widgets}
+        let iter_at = |(line, offset): (i32, i32)| buffer.iter_at_line_offset(line, offset).unwrap_or(buffer.end_iter());
+        buffer.select_range(&iter_at(cursor_position), &iter_at(selection_position));
+    }
+
+    /// Show the variable names starting like the word before the cursor,
+    /// if it has at least `min_len` chars.
+    fn suggest(&self, min_len: usize) {
+        let (prefix, start) = word_at_cursor(&self.text_buffer);
+        let names = self.names.borrow();
+        let matches = completions(&names, &prefix);
+
+        if prefix.chars().count() < min_len || matches.is_empty() {
+            self.completion.popdown();
+            return;
+        }
+
+        // below the start of the word
+        let location = self.text_view.iter_location(&start);
+        let (x, y) = self.text_view.buffer_to_window_coords(gtk::TextWindowType::Widget, location.x(), location.y());
+        let rect = gtk::gdk::Rectangle::new(x, y, location.width().max(1), location.height());
+
+        self.completion_label.set_text(&matches.join("\n"));
+        self.completion.set_pointing_to(Some(&rect));
+        self.completion.popup();
+    }
+}
+
+/// Do the edits of `edit` as a single step of the undo history, like a single action of the user
+fn user_action(buffer: &gtk::TextBuffer, edit: impl FnOnce(&gtk::TextBuffer)) {
+    buffer.begin_user_action();
+    edit(buffer);
+    buffer.end_user_action();
+}
+
+/// Replace the whole text of the `buffer`, with a new undo history: the previous document can't be restored by undo
+fn load_text(buffer: &gtk::TextBuffer, text: &str) {
+    buffer.begin_irreversible_action();
+    buffer.set_text(text);
+    buffer.end_irreversible_action();
+}
+
+/// The identifier typed before the cursor, and where it starts
+fn word_at_cursor(buffer: &gtk::TextBuffer) -> (String, gtk::TextIter) {
+    let cursor = buffer.iter_at_mark(&buffer.get_insert());
+
+    let mut start = cursor;
+    while start.backward_char() {
+        if !(start.char().is_alphanumeric() || start.char() == '_') {
+            start.forward_char();
+            break;
+        }
+    }
+
+    // the number of an implicit multiplication like `2price` isn't part of the name
+    let word = buffer.text(&start, &cursor, false);
+    let digits = word.chars().take_while(|c| c.is_ascii_digit()).count();
+    start.forward_chars(digits as i32);
+
+    (buffer.text(&start, &cursor, false).to_string(), start)
+}
+
+/// The text of `value` that gives the same value after any operator, like `2 * (1/3)`:
+/// the compound values and the negative ones are in parentheses, and a unit sticks to its number
+fn insertable(value: &ResType) -> String {
+    let text = match value {
+        // `1 m` would be the number 1 followed by the variable `m`
+        ResType::Quantity(_, _) => value.to_string().replace(' ', ""),
+        _ => value.to_string()
+    };
+
+    if matches!(value, ResType::Rational(_, _) | ResType::Complex(_, _)) || text.starts_with('-') {
+        format!("({})", text)
+    } else {
+        text
+    }
+}
+
+/// Add a `# ` before each line of the `text`, or remove it if every line is already a comment.
+/// The blank lines are left as they are.
+fn toggle_comment(text: &str) -> String {
+    let mut lines = text.split('\n').filter(|line| !line.trim().is_empty()).peekable();
+    let uncomment = lines.peek().is_some() && lines.all(|line| line.trim_start().starts_with('#'));
+
+    let lines: Vec<String> = text.split('\n')
+        .map(|line| {
+            if line.trim().is_empty() {
+                line.to_string()
+            } else if uncomment {
+                // keep the indentation before the `#`
+                let indent = line.len() - line.trim_start().len();
+                let comment = &line[indent + 1..];
+                let comment = comment.strip_prefix(' ').unwrap_or(comment);
+                format!("{}{}", &line[..indent], comment)
+            } else {
+                format!("# {}", line)
+            }
+        })
+        .collect();
+
+    lines.join("\n")
+}
+
+/// Highlight the partner of the parenthesis next to the cursor with `match_tag`,
+/// and the parentheses without a partner with `unmatched_tag`. Only the line of the cursor is scanned.
+fn highlight_parens(buffer: &gtk::TextBuffer, match_tag: &gtk::TextTag, unmatched_tag: &gtk::TextTag) {
+    let (start, end) = buffer.bounds();
+    buffer.remove_tag(match_tag, &start, &end);
+    buffer.remove_tag(unmatched_tag, &start, &end);
+
+    let cursor = buffer.iter_at_mark(&buffer.get_insert());
+    let mut line_start = cursor;
+    line_start.set_line_offset(0);
+    let mut line_end = cursor;
+    if !line_end.ends_line() {
+        line_end.forward_to_line_end();
+    }
+    let line: Vec<char> = buffer.text(&line_start, &line_end, false).chars().collect();
+
+    // the offsets in the line are chars, like the ones of the TextIter
+    let tag_char = |tag: &gtk::TextTag, index: usize| {
+        let mut start = line_start;
+        start.forward_chars(index as i32);
+        let mut end = start;
+        end.forward_char();
+        buffer.apply_tag(tag, &start, &end);
+    };
+
+    for index in unmatched_parens(&line) {
+        tag_char(unmatched_tag, index);
+    }
+
+    // the parenthesis before the cursor, or after it
+    let column = cursor.line_offset() as usize;
+    let partner = column.checked_sub(1)
+        .and_then(|before| matching_paren(&line, before))
+        .or_else(|| matching_paren(&line, column));
+    if let Some(partner) = partner {
+        tag_char(match_tag, partner);
+    }
+}
+
+/// The index of the parenthesis matching the one at `index` in the `line`,
+/// None if there's no parenthesis at `index` or it has no partner.
+fn matching_paren(line: &[char], index: usize) -> Option<usize> {
+    let (forward, open, close) = match line.get(index)? {
+        '(' => (true, '(', ')'),
+        ')' => (false, ')', '('),
+        _ => return None
+    };
+
+    let mut depth = 0;
+    let mut check = |i: usize| {
+        if line[i] == open {
+            depth += 1;
+        } else if line[i] == close {
+            depth -= 1;
+        }
+        depth == 0
+    };
+
+    if forward {
+        (index..line.len()).find(|&i| check(i))
+    } else {
+        (0..=index).rev().find(|&i| check(i))
+    }
+}
+
+/// The index of the parentheses of the `line` without a partner, like the last one of `(1 + 2))`
+fn unmatched_parens(line: &[char]) -> Vec<usize> {
+    let mut open = Vec::new();
+    let mut unmatched = Vec::new();
+
+    for (index, char) in line.iter().enumerate() {
+        if *char == '(' {
+            open.push(index);
+        } else if *char == ')' && open.pop().is_none() {
+            unmatched.push(index);
+        }
+    }
+
+    unmatched.extend(open);
+    unmatched.sort();
+    unmatched
+}
+
+/// The `names` that could complete `prefix`
+fn completions<'a>(names: &'a [String], prefix: &str) -> Vec<&'a str> {
+    if prefix.is_empty() {
+        return Vec::new();
+    }
+
+    names.iter()
+        .filter(|name| name.starts_with(prefix) && name.as_str() != prefix)
+        .map(String::as_str)
+        .collect()
+}
+
+/// The longest start shared by all the `words`
+fn common_prefix(words: &[&str]) -> String {
+    let Some((first, others)) = words.split_first() else {
+        return String::new()
+    };
+
+    let mut len = first.len();
+    for word in others {
+        len = first.char_indices()
+            .zip(word.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((i, a), _)| i + a.len_utf8())
+            .min(len);
+    }
+
+    first[..len].to_string()
+}
+
+/// Result of a line of the input
+#[derive(Clone, Debug, PartialEq)]
+pub enum LineResult {
+    /// Nothing to compute, like a blank line or a comment
+    Empty,
+    Value(String),
+    Error(String)
+}
+
+impl LineResult {
+    /// The text shown in the result pane
+    pub fn text(&self) -> &str {
+        match self {
+            LineResult::Empty => "",
+            LineResult::Value(text) | LineResult::Error(text) => text
+        }
+    }
+}
+
+/// A line of the input, with its result and the variables it gives to the next lines
+struct SolvedLine {
+    text: String,
+    result: LineResult,
+    state: LineState
+}
+
+/// Interpret every line of `text`, the variables are the ones assigned in the previous lines.
+///
+/// The lines before the first one changed since the `solved` lines give the same results,
+/// they are not interpreted again.
+fn solve_lines(
+    text: &str,
+    solved: &mut Vec<SolvedLine>,
+    rates: &HashMap<(Currency, Currency), f64>,
+    format: &FormatOptions,
+    angle_mode: AngleMode
+) -> Vec<LineResult> {
+    let lines: Vec<&str> = text.lines().collect();
+    let unchanged = solved.iter()
+        .zip(&lines)
+        .take_while(|(solved, line)| solved.text == **line)
+        .count();
+
+    // the results after the edited line may depend on it
+    solved.truncate(unchanged);
+    let mut state = solved.last().map_or_else(|| {
+        let mut state = LineState::default();
+        state.set_angle_mode(angle_mode);
+        state
+    }, |line| line.state.clone());
+
+    for (number, line) in lines.iter().enumerate().skip(unchanged) {
+        let result = match state.solve(number, line, rates) {
+            None => LineResult::Empty,
+            Some(Ok(solution)) => LineResult::Value(match solution.kind {
+                StatementKind::Assignment { name } => format!("{} = {}", name, solution.value.format(format)),
+                StatementKind::Expression => solution.value.format(format)
+            }),
+            Some(Err(message)) => LineResult::Error(message)
+        };
+        solved.push(SolvedLine { text: line.to_string(), result, state: state.clone() });
+    }
+
+    solved.iter().map(|line| line.result.clone()).collect()
+}
+
+/// The names of the variables assigned by the `solved` lines, sorted
+fn variable_names(solved: &[SolvedLine]) -> Vec<String> {
+    let mut names: Vec<String> = solved.last()
+        .map(|line| line.state.variables().keys()
+            .filter(|name| !is_line_variable(name))
+            .cloned()
+            .collect())
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Apply the `tag` to the lines of the `buffer` whose result is an error
+pub fn highlight_errors(buffer: &gtk::TextBuffer, tag: &gtk::TextTag, results: &[LineResult]) {
+    buffer.remove_tag(tag, &buffer.start_iter(), &buffer.end_iter());
+
+    for (number, result) in results.iter().enumerate() {
+        if let (LineResult::Error(_), Some(start)) = (result, buffer.iter_at_line(number as i32)) {
+            let mut end = start;
+            if !end.ends_line() {
+                end.forward_to_line_end();
+            }
+            buffer.apply_tag(tag, &start, &end);
+        }
+    }
+}
+
+/// A gutter for `text_view`, with the number of each line of the `text_buffer`.
+/// The lines don't wrap, a number is on the single row of its line.
+fn line_numbers_gutter(text_view: &gtk::TextView, text_buffer: &gtk::TextBuffer) -> gtk::DrawingArea {
+    let gutter = gtk::DrawingArea::builder()
+        .content_width(40)
+        .css_classes(["line-numbers"])
+        .build();
+
+    gutter.set_draw_func(clone!(@weak text_view => move |_, cr, width, _| {
+        let color = text_view.color();
+        cr.set_source_rgba(color.red().into(), color.green().into(), color.blue().into(), 0.5);
+
+        // same size as the text of the input
+        let font_size = text_view.pango_context().font_description()
+            .map_or(11.0, |font| font.size() as f64 / gtk::pango::SCALE as f64);
+        cr.set_font_size(font_size * 96.0 / 72.0);
+        let ascent = cr.font_extents().map_or(font_size, |extents| extents.ascent());
+
+        let visible = text_view.visible_rect();
+        let (mut line, _) = text_view.line_at_y(visible.y());
+
+        loop {
+            let (y, _) = text_view.line_yrange(&line);
+            if y > visible.y() + visible.height() {
+                break;
+            }
+            let (_, window_y) = text_view.buffer_to_window_coords(gtk::TextWindowType::Left, 0, y);
+
+            // right aligned
+            let number = (line.line() + 1).to_string();
+            let number_width = cr.text_extents(&number).map_or(0.0, |extents| extents.x_advance());
+            cr.move_to(width as f64 - number_width - 8.0, window_y as f64 + ascent);
+            _ = cr.show_text(&number);
+
+            if !line.forward_line() {
+                break;
+            }
+        }
+    }));
+
+    // redraw the numbers when the lines change or move
+    text_buffer.connect_changed(clone!(@weak gutter => move |_| gutter.queue_draw()));
+    text_view.connect_vadjustment_notify(clone!(@weak gutter => move |text_view| {
+        if let Some(adjustment) = text_view.vadjustment() {
+            adjustment.connect_value_changed(clone!(@weak gutter => move |_| gutter.queue_draw()));
+        }
+    }));
+
+    gutter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use luca::Unit;
+
+    #[test]
+    fn test_completions() {
+        let names = vec![String::from("price"), String::from("prices_2024"), String::from("rate")];
+
+        assert_eq!(completions(&names, "pr"), vec!["price", "prices_2024"]);
+        assert_eq!(completions(&names, "price"), vec!["prices_2024"]);
+        assert!(completions(&names, "x").is_empty());
+        assert_eq!(common_prefix(&completions(&names, "pr")), "price");
+        assert_eq!(common_prefix(&["rate"]), "rate");
+
+        assert!(is_line_variable("line12"));
+        assert!(!is_line_variable("lines"));
+    }
+
+    #[test]
+    fn test_insertable() {
+        assert_eq!(insertable(&ResType::Int(5)), "5");
+        assert_eq!(insertable(&ResType::Quantity(1.0, Unit::Meter)), "1m");
+        assert_eq!(insertable(&ResType::Rational(1, 3)), "(1/3)");
+        assert_eq!(insertable(&ResType::Complex(3.0, 4.0)), "(3+4i)");
+        assert_eq!(insertable(&ResType::Int(-2)), "(-2)");
+
+        // computed again to the same value
+        let solve_twice = |value: ResType| {
+            luca::solve(format!("2 * {}", insertable(&value)), Rc::new(RefCell::new(HashMap::new())), &HashMap::new())
+        };
+        assert_eq!(solve_twice(ResType::Quantity(1.5, Unit::Kilometer)), Ok(ResType::Quantity(3.0, Unit::Kilometer)));
+        assert_eq!(solve_twice(ResType::Rational(1, 3)), Ok(ResType::Float(2.0 / 3.0)));
+        assert_eq!(solve_twice(ResType::Complex(3.0, 4.0)), Ok(ResType::Complex(6.0, 8.0)));
+        assert_eq!(solve_twice(ResType::Int(-2)), Ok(ResType::Int(-4)));
+    }
+
+    #[test]
+    fn test_toggle_comment() {
+        let block = "price = 12€\n\nprice * 3";
+        let commented = toggle_comment(block);
+        assert_eq!(commented, "# price = 12€\n\n# price * 3");
+        assert_eq!(toggle_comment(&commented), block);
+
+        // a block with a line that isn't a comment yet is commented again
+        assert_eq!(toggle_comment("# note\n2 + 2"), "# # note\n# 2 + 2");
+        assert_eq!(toggle_comment("  #indented"), "  indented");
+        assert_eq!(toggle_comment(""), "");
+    }
+
+    #[test]
+    fn test_matching_parens() {
+        let line: Vec<char> = "2 * (3 + (4 - 1)) + (5".chars().collect();
+
+        assert_eq!(matching_paren(&line, 4), Some(16));
+        assert_eq!(matching_paren(&line, 16), Some(4));
+        assert_eq!(matching_paren(&line, 9), Some(15));
+        assert_eq!(matching_paren(&line, 15), Some(9));
+        assert_eq!(matching_paren(&line, 20), None);
+        assert_eq!(matching_paren(&line, 0), None);
+        assert_eq!(matching_paren(&line, 100), None);
+
+        assert_eq!(unmatched_parens(&line), vec![20]);
+        assert_eq!(unmatched_parens(&"1)) + (€".chars().collect::<Vec<char>>()), vec![1, 2, 6]);
+    }
+
+    #[test]
+    fn test_solve_lines_errors() {
+        let text = "1 + 1\n2 * )\n\n# a note\nunknown\nans * 3\nx = 5 + 2";
+
+        let results = solve_lines(text, &mut Vec::new(), &HashMap::new(), &FormatOptions::default(), AngleMode::Radians);
+        let errors: Vec<bool> = results.iter().map(|result| matches!(result, LineResult::Error(_))).collect();
+
+        assert_eq!(errors, vec![false, true, false, false, true, false, false]);
+        assert_eq!(results[0], LineResult::Value(String::from("2")));
+        assert_eq!(results[2], LineResult::Empty);
+        assert_eq!(results[5], LineResult::Value(String::from("6")));
+        assert_eq!(results[6], LineResult::Value(String::from("x = 7")));
+    }
+
+    #[test]
+    fn test_solve_lines_booleans() {
+        let text = "3 < 5\n10€ == 5€\n2\ntotal";
+
+        let results = solve_lines(text, &mut Vec::new(), &HashMap::new(), &FormatOptions::default(), AngleMode::Radians);
+
+        assert_eq!(results[0], LineResult::Value(String::from("true")));
+        assert_eq!(results[1], LineResult::Value(String::from("false")));
+        assert_eq!(results[3], LineResult::Value(String::from("2")));
+    }
+
+    #[test]
+    fn test_solve_lines_vars() {
+        let text = "vars\na = 1\nb = 2 * a\n3 + b\nvars";
+
+        let results = solve_lines(text, &mut Vec::new(), &HashMap::new(), &FormatOptions::default(), AngleMode::Radians);
+
+        assert_eq!(results[0], LineResult::Value(String::from("No variables")));
+        assert_eq!(results[4], LineResult::Value(String::from("a = 1, b = 2")));
+    }
+
+    #[test]
+    fn test_solve_lines_incremental() {
+        let rates = HashMap::new();
+        let format = FormatOptions::default();
+        let mut solved = Vec::new();
+
+        let text = "a = 2\nb = a * 3\n\nc = b + 1\nline2 + total\nc * ans";
+        solve_lines(text, &mut solved, &rates, &format, AngleMode::Radians);
+
+        // only the lines from the edited one are solved again
+        let edited = "a = 2\nb = a * 3\n\nc = b + 10\nline2 + total\nc * ans";
+        let results = solve_lines(edited, &mut solved, &rates, &format, AngleMode::Radians);
+        assert_eq!(results, solve_lines(edited, &mut Vec::new(), &rates, &format, AngleMode::Radians));
+        assert_eq!(results[5], LineResult::Value(String::from("480")));
+
+        // the lines after an earlier edit are updated
+        let edited = "a = 5\nb = a * 3\n\nc = b + 10\nline2 + total\nc * ans";
+        let results = solve_lines(edited, &mut solved, &rates, &format, AngleMode::Radians);
+        assert_eq!(results, solve_lines(edited, &mut Vec::new(), &rates, &format, AngleMode::Radians));
+
+        // removed and added lines
+        let edited = "a = 5\nb = a * 3\nb";
+        let results = solve_lines(edited, &mut solved, &rates, &format, AngleMode::Radians);
+        assert_eq!(results, solve_lines(edited, &mut Vec::new(), &rates, &format, AngleMode::Radians));
+        assert_eq!(solved.len(), 3);
+    }
+
+    /// The time to solve a 200-line document again after typing on its last line:
+    /// `cargo test --release bench_solve_lines -- --ignored --nocapture`
+    #[test]
+    #[ignore]
+    fn bench_solve_lines() {
+        let (rates, format) = (HashMap::new(), FormatOptions::default());
+        let mut lines = vec![String::from("v0 = 12€")];
+        lines.extend((1..199).map(|n| format!("v{} = v{} * 1.01 + {}€ # line {}", n, n - 1, n, n + 1)));
+        let text = lines.join("\n");
+
+        let time = |solved: &mut Vec<SolvedLine>, incremental: bool| {
+            let start = std::time::Instant::now();
+            for n in 0..100 {
+                let edited = format!("{}\nv198 * {}", text, n);
+                if !incremental {
+                    solved.clear();
+                }
+                solve_lines(&edited, solved, &rates, &format, AngleMode::Radians);
+            }
+            start.elapsed() / 100
+        };
+
+        // every line solved on each keystroke, like before the solved lines were kept
+        let full = time(&mut Vec::new(), false);
+        let mut solved = Vec::new();
+        solve_lines(&text, &mut solved, &rates, &format, AngleMode::Radians);
+        let incremental = time(&mut solved, true);
+
+        println!("200 lines: {:?} solving every line, {:?} from the edited line", full, incremental);
+        assert!(incremental < full);
     }
 
-    // fn update(&mut self, msgInput: Self::Input, _sender: ComponentSender<Self>) {
-    //     match msg {
-    //         Msg::TextChanged(text) => {
-    //             self.text = text;
-    //             if let Ok(res) = solve(self.text.clone()) {
-    //                 println!("{}", res);
-    //             }
-    //         }
-    //     }
-    // }
+    #[test]
+    fn test_clear() {
+        let (rates, format) = (HashMap::new(), FormatOptions::default());
+        let mut solved = Vec::new();
+
+        solve_lines("price = 12€\nqty = 3\nprice * qty", &mut solved, &rates, &format, AngleMode::Radians);
+        assert_eq!(variable_names(&solved), vec![String::from("price"), String::from("qty")]);
+
+        // the cleared document has no result, and forgets its variables
+        assert!(solve_lines("", &mut solved, &rates, &format, AngleMode::Radians).is_empty());
+        assert!(solved.is_empty());
+        assert!(variable_names(&solved).is_empty());
+
+        let results = solve_lines("price", &mut solved, &rates, &format, AngleMode::Radians);
+        assert_eq!(results, vec![LineResult::Error(String::from("Undefined variable: price"))]);
+    }
+
+    #[test]
+    fn test_solve_lines_angle_mode() {
+        let text = "a = sin(90)\na + cos(0)";
+        let results = solve_lines(text, &mut Vec::new(), &HashMap::new(), &FormatOptions::default(), AngleMode::Degrees);
+        assert_eq!(results, vec![LineResult::Value(String::from("a = 1.0")), LineResult::Value(String::from("2.0"))]);
+    }
 }
@@ -1,29 +1,71 @@
 use core::f64;
-use std::collections::HashMap;
-use std::{i128, io};
-use std::io::Write;
+use std::collections::{HashMap, HashSet};
+use std::i128;
 use std::rc::Rc;
 use std::cell::RefCell;
-use std::ops::{Add, Sub, Neg, Mul, Div};
+use std::ops::{Add, Sub, Neg, Mul, Div, Rem};
 use std::fmt;
-
-
-#[derive(Debug, Eq, PartialEq)]
-enum Error {
-    InvalidSyntax,
-    UndefinedVariable,
+use std::cmp::Ordering;
+use rand::Rng;
+#[cfg(feature = "bigint")]
+use num_bigint::{BigInt, Sign};
+#[cfg(feature = "bigint")]
+use num_traits::{Euclid, Signed, ToPrimitive, Zero};
+
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Error {
+    InvalidSyntax(usize), // Position (in chars) of the token we could not understand
+    UndefinedVariable(String, Option<String>), // Name of the variable, and the closest defined name
     DivisonByZero,
-    IncorrectFloat // Could not parse the float
+    IncorrectFloat, // Could not parse the float
+    UnknownFunction,
+    WrongArgumentCount, // Incorrect number of arguments given to a function
+    Overflow, // The result doesn't fit in an i128
+    NoConversionRate, // We don't know how to convert between two currencies
+    OutOfDomain, // The value is outside of the function domain, like sqrt(-1)
+    CircularReference, // A variable needs its own value to be computed: a = b + 1, b = a + 1
+    IncompatibleUnits, // The units measure different things, like 1m + 1h
+    ProtectedConstant // The built-in constants like pi can't be assigned
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidSyntax(pos) => write!(f, "Invalid syntax at column {}", pos + 1),
+            Error::UndefinedVariable(name, None) => write!(f, "Undefined variable: {}", name),
+            Error::UndefinedVariable(name, Some(suggestion)) => write!(f, "Undefined variable: {}, did you mean {}?", name, suggestion),
+            Error::DivisonByZero => write!(f, "Division by zero"),
+            Error::IncorrectFloat => write!(f, "Incorrect number"),
+            Error::UnknownFunction => write!(f, "Unknown function"),
+            Error::WrongArgumentCount => write!(f, "Wrong number of arguments"),
+            Error::Overflow => write!(f, "Overflow"),
+            Error::NoConversionRate => write!(f, "No conversion rate"),
+            Error::OutOfDomain => write!(f, "Out of the function domain"),
+            Error::CircularReference => write!(f, "Circular reference"),
+            Error::IncompatibleUnits => write!(f, "Incompatible units"),
+            Error::ProtectedConstant => write!(f, "Constants can't be assigned")
+        }
+    }
 }
 
 /*
 Our grammar is the following:
 
-statement   : expr | assignement
-assignment  : VAR ASSIGN expr
-expr        : term   ((PLUS | MINUS) term)*
-term        : factor ((MUL  | DIV) factor)*
-factor      : INTEGER | LPAREN expr RPAREN | VAR
+program     : statement (SEMICOLON statement)* SEMICOLON?
+statement   : ternary | assignement | deletion
+deletion    : DEL VAR
+assignment  : VAR (ASSIGN | PLUSASSIGN | MINUSASSIGN | MULASSIGN | DIVASSIGN) ternary
+ternary     : comparison (QUESTION ternary COLON ternary)?
+comparison  : bitwise ((LT | GT | LE | GE | EQ | NE) bitwise)?
+bitwise     : shift  ((AND | OR | XOR) shift)*
+shift       : expr   ((SHL | SHR) expr)*
+expr        : term   ((PLUS | MINUS) term)* (IN (MONEY | UNIT))?
+term        : postfix (VAR | postfix)* ((MUL  | DIV | INTDIV | MOD) postfix)* | postfix OF postfix
+postfix     : factor (FACT | PERCENT)*
+factor      : (PLUS | MINUS) factor | power
+power       : atom (POW factor)?
+atom        : INTEGER | DATE | DURATION | QUANTITY | IMAGINARY | LPAREN ternary RPAREN | VAR | FUNC LPAREN (ternary (COMMA ternary)*)? RPAREN
 
 */
 
@@ -43,22 +85,105 @@ factor      : INTEGER | LPAREN expr RPAREN | VAR
 enum Token {
     INTEGER(i128),
     FLOAT(f64),
+    // days since 1970-01-01
+    DATE(i64),
+    // seconds
+    DURATION(f64),
+    QUANTITY(f64, Unit),
+    // the imaginary part of a complex number, like `4i`
+    IMAGINARY(f64),
+    // only in the AST, the target of a conversion like `in km`
+    UNIT(Unit),
     PLUS,
     MINUS,
     MUL,
     DIV,
+    INTDIV,
+    MOD,
+    AND,
+    OR,
+    XOR,
+    SHL,
+    SHR,
+    LT,
+    GT,
+    LE,
+    GE,
+    EQ,
+    NE,
+    PERCENT,
+    OF,
+    IN,
+    FACT,
+    POW,
     LPAREN,
     RPAREN,
+    COMMA,
+    SEMICOLON,
+    QUESTION,
+    COLON,
     ASSIGN,
+    PLUSASSIGN,
+    MINUSASSIGN,
+    MULASSIGN,
+    DIVASSIGN,
+    DEL,
     VAR(String),
+    FUNC(String),
     MONEY(Currency),
     EOF,
 }
 
-#[derive(Debug, Clone, PartialEq, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
 pub enum Currency {
     Euro,
-    Dollar
+    Dollar,
+    Pound,
+    Yen,
+    Bitcoin
+}
+
+impl Currency {
+    /// The ISO 4217 code of the currency, like EUR
+    pub fn code(&self) -> &'static str {
+        match self {
+            Currency::Euro => "EUR",
+            Currency::Dollar => "USD",
+            Currency::Pound => "GBP",
+            Currency::Yen => "JPY",
+            Currency::Bitcoin => "BTC"
+        }
+    }
+
+    /// The currency of an ISO 4217 code, like the `USD` of `100 USD`
+    pub fn from_code(code: &str) -> Option<Currency> {
+        match code {
+            "EUR" => Some(Currency::Euro),
+            "USD" => Some(Currency::Dollar),
+            "GBP" => Some(Currency::Pound),
+            "JPY" => Some(Currency::Yen),
+            "BTC" => Some(Currency::Bitcoin),
+            _ => None
+        }
+    }
+
+    /// Where the symbol of this currency is usually written: `$100` but `100 €`
+    pub fn symbol_placement(&self) -> SymbolPlacement {
+        match self {
+            Currency::Dollar | Currency::Pound | Currency::Yen => SymbolPlacement::Before,
+            Currency::Euro | Currency::Bitcoin => SymbolPlacement::After
+        }
+    }
+
+    /// Number of decimals used to display an amount in this currency
+    fn decimals(&self) -> usize {
+        match self {
+            Currency::Yen => 0,
+            // down to the satoshi
+            Currency::Bitcoin => 8,
+            _ => 2
+        }
+    }
 }
 
 impl fmt::Display for Currency {
@@ -66,15 +191,184 @@ impl fmt::Display for Currency {
         let symbol = match self {
             Currency::Euro => '€',
             Currency::Dollar => '$',
+            Currency::Pound => '£',
+            Currency::Yen => '¥',
+            Currency::Bitcoin => '₿',
         };
         write!(f, "{}", symbol)
     }
 }
 
+/// How the products and the quotients of an amount of money are rounded to the cents of its currency
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub enum MoneyRounding {
+    /// Keep every decimal, `10€ / 3` is 3.3333333333333335 €
+    #[default]
+    Exact,
+    /// The halves are rounded away from zero, 0.125 € is 0.13 €
+    HalfUp,
+    /// The halves are rounded to the even cent, 0.125 € is 0.12 €
+    HalfEven,
+    /// Round down, 0.129 € is 0.12 €
+    Floor
+}
+
+/// The side of the amount where the currency symbol is written
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum SymbolPlacement {
+    /// `$100.00`
+    Before,
+    /// `100.00 $`
+    After
+}
+
+/// A unit of measurement, like the `km` of `5km`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
+pub enum Unit {
+    Meter,
+    Centimeter,
+    Kilometer,
+    Foot,
+    Inch,
+    Mile,
+    Celsius,
+    Fahrenheit,
+    Kelvin,
+    Byte,
+    Kilobyte,
+    Megabyte,
+    Gigabyte,
+    Terabyte,
+    Kibibyte,
+    Mebibyte,
+    Gibibyte,
+    Tebibyte
+}
+
+/// What a unit measures, only the units of the same dimension can be converted
+#[derive(Debug, PartialEq, Copy, Clone)]
+enum Dimension {
+    Length,
+    Temperature,
+    Data
+}
+
+impl Unit {
+    const ALL: [Unit; 18] = [
+        Unit::Meter, Unit::Centimeter, Unit::Kilometer, Unit::Foot, Unit::Inch, Unit::Mile,
+        Unit::Celsius, Unit::Fahrenheit, Unit::Kelvin,
+        Unit::Byte, Unit::Kilobyte, Unit::Megabyte, Unit::Gigabyte, Unit::Terabyte,
+        Unit::Kibibyte, Unit::Mebibyte, Unit::Gibibyte, Unit::Tebibyte
+    ];
+
+    /// The data sizes from the smallest to the biggest, with the decimal and the binary prefixes
+    const DECIMAL_SIZES: [Unit; 5] = [Unit::Byte, Unit::Kilobyte, Unit::Megabyte, Unit::Gigabyte, Unit::Terabyte];
+    const BINARY_SIZES: [Unit; 5] = [Unit::Byte, Unit::Kibibyte, Unit::Mebibyte, Unit::Gibibyte, Unit::Tebibyte];
+
+    /// The unit written `name`, like `km`
+    fn from_name(name: &str) -> Option<Unit> {
+        Unit::ALL.into_iter().find(|unit| unit.to_string() == name)
+    }
+
+    fn dimension(&self) -> Dimension {
+        match self {
+            Unit::Celsius | Unit::Fahrenheit | Unit::Kelvin => Dimension::Temperature,
+            Unit::Byte | Unit::Kilobyte | Unit::Megabyte | Unit::Gigabyte | Unit::Terabyte
+                | Unit::Kibibyte | Unit::Mebibyte | Unit::Gibibyte | Unit::Tebibyte => Dimension::Data,
+            _ => Dimension::Length
+        }
+    }
+
+    /// Size of the unit in the base unit of its dimension, the meter for the lengths and the byte for the data.
+    /// The temperatures have an offset and aren't converted with a factor, see `celsius`.
+    ///
+    /// With `binary`, 1KB is 1024 bytes like 1KiB, instead of 1000 bytes.
+    fn factor(&self, binary: bool) -> f64 {
+        let kilo: f64 = if binary { 1024.0 } else { 1000.0 };
+
+        match self {
+            Unit::Meter => 1.0,
+            Unit::Centimeter => 0.01,
+            Unit::Kilometer => 1000.0,
+            Unit::Foot => 0.3048,
+            Unit::Inch => 0.0254,
+            Unit::Mile => 1609.344,
+            Unit::Celsius | Unit::Fahrenheit | Unit::Kelvin => 1.0,
+            Unit::Byte => 1.0,
+            Unit::Kilobyte => kilo,
+            Unit::Megabyte => kilo.powi(2),
+            Unit::Gigabyte => kilo.powi(3),
+            Unit::Terabyte => kilo.powi(4),
+            Unit::Kibibyte => 1024.0,
+            Unit::Mebibyte => 1024.0_f64.powi(2),
+            Unit::Gibibyte => 1024.0_f64.powi(3),
+            Unit::Tebibyte => 1024.0_f64.powi(4)
+        }
+    }
+
+    /// Convert a temperature in this unit to degrees Celsius
+    fn celsius(&self, val: f64) -> f64 {
+        match self {
+            Unit::Fahrenheit => (val - 32.0) * 5.0 / 9.0,
+            Unit::Kelvin => val - 273.15,
+            _ => val
+        }
+    }
+
+    /// Convert a temperature in degrees Celsius to this unit
+    fn temperature(&self, val: f64) -> f64 {
+        match self {
+            Unit::Fahrenheit => val * 9.0 / 5.0 + 32.0,
+            Unit::Kelvin => val + 273.15,
+            _ => val
+        }
+    }
+}
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Unit::Meter => "m",
+            Unit::Centimeter => "cm",
+            Unit::Kilometer => "km",
+            Unit::Foot => "ft",
+            Unit::Inch => "in",
+            Unit::Mile => "mi",
+            Unit::Celsius => "C",
+            Unit::Fahrenheit => "F",
+            Unit::Kelvin => "K",
+            Unit::Byte => "B",
+            Unit::Kilobyte => "KB",
+            Unit::Megabyte => "MB",
+            Unit::Gigabyte => "GB",
+            Unit::Terabyte => "TB",
+            Unit::Kibibyte => "KiB",
+            Unit::Mebibyte => "MiB",
+            Unit::Gibibyte => "GiB",
+            Unit::Tebibyte => "TiB"
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The units of the durations and their number of seconds, from the biggest to the smallest
+const DURATION_UNITS: [(&str, f64); 4] = [("d", 86400.0), ("h", 3600.0), ("min", 60.0), ("s", 1.0)];
+
 #[derive(Debug, Clone)]
-struct Lexer {
-    text: String,
-    pos: usize
+pub struct Lexer {
+    // The input is stored as chars, so `pos` is a char index and not a byte index,
+    // which matters as soon as the input contains a multi-byte char like `€`.
+    text: Vec<char>,
+    pos: usize,
+    // Position of the first char of the last token, used to report errors
+    token_start: usize,
+    // For each open parenthesis, if it's the one of a function call: the commas separate
+    // the arguments inside, and the thousands outside like `1,000`.
+    parentheses: Vec<bool>,
+    // The last token is the name of a function, the next one is its parenthesis
+    function_call: bool,
+    // The names of the defined variables, they aren't units after a number
+    variables: HashSet<String>
 }
 
 /// The Lexer is in charge of spliting the input in a bunch of tokens.
@@ -82,11 +376,20 @@ impl Lexer {
     pub fn new(text: String) -> Lexer {
 
         Lexer {
-            text: text,
-            pos: 0
+            text: text.chars().collect(),
+            pos: 0,
+            token_start: 0,
+            parentheses: Vec::new(),
+            function_call: false,
+            variables: HashSet::new()
         }
     }
 
+    /// The names of the defined variables: with a variable `h`, `2h` is `2 * h` instead of 2 hours
+    pub fn set_variables(&mut self, names: HashSet<String>) {
+        self.variables = names;
+    }
+
     /// Advance the `pos` pointer and set the `current_char` variable.
     fn advance(&mut self) {
         self.pos += 1
@@ -94,20 +397,89 @@ impl Lexer {
 
     /// Return the char at the `pos` position
     fn get_char(&self) -> Option<char> {
-        self.text.chars().nth(self.pos)
+        self.text.get(self.pos).copied()
+    }
+
+    /// Return the char `offset` positions after `pos`, without advancing
+    fn peek_char(&self, offset: usize) -> Option<char> {
+        self.text.get(self.pos + offset).copied()
     }
 
     /// advance `self.pos` until the next non-whitespace character
     fn skip_whitespace(&mut self) {
 
-        while self.pos < self.text.len() && self.text.chars().nth(self.pos).unwrap().is_whitespace() {
+        while self.pos < self.text.len() && self.text[self.pos].is_whitespace() {
             self.pos += 1;
         }
     }
 
+    /// Return true if the `%` that was just consumed is a percentage, like in `20%`
+    /// or `20% of 100`, and false if it's the modulo operator, like in `17 % 5`.
+    ///
+    /// A percentage is followed by the end of the input, an operator, a comparison,
+    /// the end of a statement, a closing parenthesis or the `of` keyword.
+    ///
+    /// A `+` or `-` is ambiguous, it's also the sign of the right operand of a modulo.
+    /// The `%` is then a percentage only when it sits right after its number, and the
+    /// sign starts a new operand: `50% - 10` and `50%-10`, but not `17 % -5`.
+    fn is_percent(&self) -> bool {
+        let mut next = self.pos;
+        while next < self.text.len() && self.text[next].is_whitespace() {
+            next += 1;
+        }
+
+        match self.text.get(next..) {
+            None | Some([]) => true,
+            Some(['+' | '-', after, ..]) => {
+                let attached = self.pos >= 2 && !self.text[self.pos - 2].is_whitespace();
+                let unary_sign = next > self.pos && !after.is_whitespace();
+                attached && !unary_sign
+            },
+            Some([')' | ',' | '+' | '-' | '*' | '/' | '^' | '%' | '#' | ';' | '<' | '>' | '=' | '!' | '?' | ':' | '&' | '|', ..]) => true,
+            Some(['o', 'f', after, ..]) => !after.is_alphanumeric(),
+            Some(['o', 'f']) => true,
+            Some(_) => false
+        }
+    }
+
+    /// Tell if the comma at the current position is followed by a group of thousands, like in `1,234`.
+    ///
+    /// The commas in the parentheses of a function call are the separators of its arguments,
+    /// even in `max(1,000)`.
+    fn is_thousands_separator(&self) -> bool {
+        let is_digit = |offset: usize| matches!(self.peek_char(offset), Some(c) if c.is_ascii_digit());
+
+        !self.parentheses.contains(&true) && (1..=3).all(is_digit) && !is_digit(4)
+    }
+
     /// Return a (multidigit) Token::INTEGER or TOKEN::FLOAT consumed from the input.
+    ///
+    /// Digits can be separated with underscores, like `1_000_000`.
+    /// Scientific notation like `1e6` or `2.5e-3` is always a Token::FLOAT.
+    /// The `e` is only part of the number when digits follow it, so `3e`
+    /// is the number `3` followed by the variable `e`.
+    ///
+    /// A number directly followed by a unit is a Token::DURATION or a Token::QUANTITY, like `90min` or `5km`.
+    /// A number directly followed by `i` is a Token::IMAGINARY, like `4i`.
+    /// A defined variable wins over the unit and the `i`, the number is multiplied by the variable.
     fn number(&mut self) -> Result<Token, Error> {
+
+        // hexadecimal (0xff) and binary (0b1010) integers
+        if self.get_char() == Some('0') {
+            let radix = match self.peek_char(1) {
+                Some('x' | 'X') => Some(16),
+                Some('b' | 'B') => Some(2),
+                _ => None
+            };
+
+            if let Some(radix) = radix {
+                return self.radix_number(radix);
+            }
+        }
+
         let mut is_float = false;
+        // the thousands are separated by commas, like 1,234,567.89
+        let mut grouped = false;
 
         let mut ascii_number = String::from("");
 
@@ -120,95 +492,323 @@ impl Lexer {
                     is_float = true;
                     self.advance();
                     ascii_number.push(char);
+                } else if char == ',' && !is_float && (grouped || ascii_number.len() <= 3) && self.is_thousands_separator() {
+                    // the first group has up to 3 digits, the next ones exactly 3
+                    grouped = true;
+                    self.advance();
+                } else if char == '_' {
+                    // digit separator: 1_000_000, only allowed between two digits
+                    let after_digit = ascii_number.ends_with(|c: char| c.is_ascii_digit());
+                    let before_digit = matches!(self.peek_char(1), Some(c) if c.is_ascii_digit());
+
+                    if !after_digit || !before_digit {
+                        return Err(Error::InvalidSyntax(self.pos));
+                    }
+                    self.advance();
                 } else {
                     break;
                 }
         }
 
-        match is_float {
+        // exponent: e6, E2, e-3, e+3
+        if let Some(char) = self.get_char() {
+            if char == 'e' || char == 'E' {
+                let exponent_len = match (self.peek_char(1), self.peek_char(2)) {
+                    (Some(digit), _) if digit.is_ascii_digit() => Some(1),
+                    (Some('+' | '-'), Some(digit)) if digit.is_ascii_digit() => Some(2),
+                    _ => None
+                };
+
+                if let Some(len) = exponent_len {
+                    is_float = true;
+                    for _ in 0..len {
+                        ascii_number.push(self.get_char().unwrap());
+                        self.advance();
+                    }
+                    while let Some(digit) = self.get_char() {
+                        if !digit.is_ascii_digit() {
+                            break;
+                        }
+                        ascii_number.push(digit);
+                        self.advance();
+                    }
+                }
+            }
+        }
+
+        let number = match is_float {
             false => {
-                let val: i128 = i128::from_str_radix(&ascii_number, 10).unwrap();
-                Ok(Token::INTEGER(val))
+                // only digits at this point, so it can only fail if the number is too big
+                match ascii_number.parse::<i128>() {
+                    Ok(val) => Token::INTEGER(val),
+                    Err(_) => return Err(Error::Overflow)
+                }
             },
             true => {
                 if let Ok(val) = &ascii_number.parse::<f64>() {
-                    Ok(Token::FLOAT(*val))
+                    Token::FLOAT(*val)
                 } else {
-                    Err(Error::IncorrectFloat)
+                    return Err(Error::IncorrectFloat)
                 }
             }
+        };
+
+        let value = match number {
+            Token::INTEGER(val) => val as f64,
+            Token::FLOAT(val) => val,
+            _ => unreachable!()
+        };
+
+        let Some(name) = self.unit_name() else {
+            return Ok(number)
+        };
+        if self.variables.contains(&name) {
+            return Ok(number);
+        }
+
+        // `4i` is imaginary, but `2in` is a length and `2 i` is the number 2 followed by the variable `i`
+        let ends_word = !matches!(self.peek_char(1), Some(c) if c.is_alphanumeric() || c == '_');
+        if self.get_char() == Some('i') && ends_word {
+            self.advance();
+            return Ok(Token::IMAGINARY(value));
+        }
+
+        let token = if let Some((_, seconds)) = DURATION_UNITS.iter().find(|(unit, _)| *unit == name) {
+            Token::DURATION(value * seconds)
+        } else if let Some(unit) = Unit::from_name(&name) {
+            Token::QUANTITY(value, unit)
+        } else {
+            return Ok(number)
+        };
+        self.pos += name.chars().count();
+        Ok(token)
+    }
+
+    /// Return the letters right after a number without consuming them, like `min` in `90min`.
+    /// `2 h` is the number 2 followed by the variable `h`, and there's no unit in `2ab1`.
+    fn unit_name(&self) -> Option<String> {
+        let end = (self.pos..self.text.len())
+            .find(|&i| !self.text[i].is_alphabetic())
+            .unwrap_or(self.text.len());
+        let ends_word = !matches!(self.text.get(end), Some(c) if c.is_alphanumeric() || *c == '_');
+
+        if end == self.pos || !ends_word {
+            return None;
+        }
+        Some(self.text[self.pos..end].iter().collect())
+    }
+
+    /// Return a Token::DATE if the input continues with an ISO date like `2024-01-15`,
+    /// and None if it's just a number, like in `2024-01`.
+    fn date(&mut self) -> Result<Option<Token>, Error> {
+        let Some(chars) = self.text.get(self.pos..self.pos + 10) else {
+            return Ok(None)
+        };
+
+        let is_date = chars.iter().enumerate()
+            .all(|(i, c)| if i == 4 || i == 7 { *c == '-' } else { c.is_ascii_digit() });
+        // 2024-01-150 isn't a date
+        let digit_after = matches!(self.peek_char(10), Some(c) if c.is_ascii_digit());
+        if !is_date || digit_after {
+            return Ok(None);
+        }
+
+        let text: String = chars.iter().collect();
+        let year = text[0..4].parse().unwrap();
+        let month = text[5..7].parse().unwrap();
+        let day = text[8..10].parse().unwrap();
+        let days = days_from_date(year, month, day).ok_or(Error::InvalidSyntax(self.token_start))?;
+
+        self.pos += 10;
+        Ok(Some(Token::DATE(days)))
+    }
+
+    /// Return a Token::INTEGER written in base `radix`, like `0xff` or `0b1010`.
+    fn radix_number(&mut self, radix: u32) -> Result<Token, Error> {
+        // skip the `0x` / `0b` prefix
+        self.advance();
+        self.advance();
+
+        // consume every alphanumeric char, so `0xG` is an error and not `0 * xG`
+        let mut digits = String::from("");
+        while let Some(char) = self.get_char() {
+            if !char.is_alphanumeric() {
+                break;
+            }
+            digits.push(char);
+            self.advance();
         }
 
+        match i128::from_str_radix(&digits, radix) {
+            Ok(val) => Ok(Token::INTEGER(val)),
+            Err(_) => Err(Error::InvalidSyntax(self.token_start))
+        }
     }
 
-    /// Retun a string
+    /// Return the name of a variable or a function.
+    ///
+    /// A name starts with a letter or an underscore, followed by letters,
+    /// digits and underscores: `rate_1`, `total_2024`.
     fn variable(&mut self) -> String {
         let str_start = self.pos;
-        let input_text: String = self.text.chars().skip(self.pos).collect();
 
-        let end_of_variable = input_text
-            .find(|c: char| c == '=' || c == '€' || c == '$'
-                || c == '+' || c == '-' || c == '*' || c == '/'
-                || c.is_whitespace())
-            .unwrap_or(input_text.len());
+        while let Some(c) = self.get_char() {
+            if !(c.is_alphanumeric() || c == '_') {
+                break;
+            }
+            self.advance();
+        }
+
+        self.text[str_start..self.pos].iter().collect()
+    }
 
-        
-        self.pos = str_start + end_of_variable;
-        
-        let new_var: String = input_text.chars().take(end_of_variable).collect();
-        // println!("new_var: {:?}", new_var);
-        new_var
+    /// Return `assignment` if the operator that has just been consumed is followed by `=`,
+    /// like `+=`, and `operator` otherwise.
+    fn compound_assignment(&mut self, operator: Token, assignment: Token) -> Token {
+        if self.get_char() == Some('=') {
+            self.advance();
+            assignment
+        } else {
+            operator
+        }
     }
 
     /// Lexical analyser (also known as scanner or tokenizer).
     ///    
     /// This method is responsible for breaking a sentence
     /// appart into tokens. One token at the time.
-    pub fn get_next_token(&mut self) -> Result<Token, Error> {
+    fn get_next_token(&mut self) -> Result<Token, Error> {
 
         // get the next non-whitespace char, or EOF
         let char = loop {
             let my_char = self.get_char();
             match my_char {
-                None => return Ok(Token::EOF),
+                None => {
+                    self.token_start = self.pos;
+                    return Ok(Token::EOF)
+                },
                 Some(char) if char.is_whitespace() => {
                     self.skip_whitespace()
                 },
                 Some(char) => break char
             }
         };
+        self.token_start = self.pos;
 
         match char {
             char if char.is_ascii_digit() => {
+                if let Some(date) = self.date()? {
+                    return Ok(date);
+                }
                 Ok(self.number()?)
             },
             '+' => {
                 self.advance();
-                Ok(Token::PLUS)
+                Ok(self.compound_assignment(Token::PLUS, Token::PLUSASSIGN))
             },
             '-' => {
                 self.advance();
-                Ok(Token::MINUS)
+                Ok(self.compound_assignment(Token::MINUS, Token::MINUSASSIGN))
             },    
             '*' => {
                 self.advance();
-                Ok(Token::MUL,)
+                Ok(self.compound_assignment(Token::MUL, Token::MULASSIGN))
+            },
+            '^' => {
+                self.advance();
+                Ok(Token::POW)
             },    
             '/' => {
                 self.advance();
-                Ok(Token::DIV,)
+                // `//` is the integer division
+                if self.get_char() == Some('/') {
+                    self.advance();
+                    Ok(Token::INTDIV)
+                } else {
+                    Ok(self.compound_assignment(Token::DIV, Token::DIVASSIGN))
+                }
             },    
+            '%' => {
+                self.advance();
+                if self.is_percent() {
+                    Ok(Token::PERCENT)
+                } else {
+                    Ok(Token::MOD)
+                }
+            },
+            '!' => {
+                self.advance();
+                if self.get_char() == Some('=') {
+                    self.advance();
+                    Ok(Token::NE)
+                } else {
+                    Ok(Token::FACT)
+                }
+            },
+            '&' => {
+                self.advance();
+                Ok(Token::AND)
+            },
+            '|' => {
+                self.advance();
+                Ok(Token::OR)
+            },
+            '<' => {
+                self.advance();
+                match self.get_char() {
+                    Some('<') => { self.advance(); Ok(Token::SHL) },
+                    Some('=') => { self.advance(); Ok(Token::LE) },
+                    _ => Ok(Token::LT)
+                }
+            },
+            '>' => {
+                self.advance();
+                match self.get_char() {
+                    Some('>') => { self.advance(); Ok(Token::SHR) },
+                    Some('=') => { self.advance(); Ok(Token::GE) },
+                    _ => Ok(Token::GT)
+                }
+            },
             '(' => {
+                let call = std::mem::take(&mut self.function_call);
+                self.parentheses.push(call);
                 self.advance();
                 Ok(Token::LPAREN)
             },    
             ')' => {
+                self.parentheses.pop();
                 self.advance();
                 Ok(Token::RPAREN)
             },
+            ',' => {
+                self.advance();
+                Ok(Token::COMMA)
+            },
+            ';' => {
+                self.advance();
+                Ok(Token::SEMICOLON)
+            },
+            '?' => {
+                self.advance();
+                Ok(Token::QUESTION)
+            },
+            ':' => {
+                self.advance();
+                Ok(Token::COLON)
+            },
             '=' => {
                 self.advance();
-                Ok(Token::ASSIGN)
+                if self.get_char() == Some('=') {
+                    self.advance();
+                    Ok(Token::EQ)
+                } else {
+                    Ok(Token::ASSIGN)
+                }
+            },
+            '#' => {
+                // a comment, until the end of the line
+                self.pos = self.text.len();
+                Ok(Token::EOF)
             },
             '€' => {
                 self.advance();
@@ -218,10 +818,39 @@ impl Lexer {
                 self.advance();
                 Ok(Token::MONEY(Currency::Dollar))
             },
-            char if char.is_alphabetic() => {
-                Ok(Token::VAR(self.variable()))
+            '£' => {
+                self.advance();
+                Ok(Token::MONEY(Currency::Pound))
+            },
+            '¥' => {
+                self.advance();
+                Ok(Token::MONEY(Currency::Yen))
+            },
+            '₿' => {
+                self.advance();
+                Ok(Token::MONEY(Currency::Bitcoin))
+            },
+            char if char.is_alphabetic() || char == '_' => {
+                let name = self.variable();
+
+                // a name directly followed by a parenthesis is a function call: sqrt(2)
+                match self.get_char() {
+                    Some('(') => {
+                        self.function_call = true;
+                        Ok(Token::FUNC(name))
+                    },
+                    _ if name == "of" => Ok(Token::OF),
+                    _ if name == "in" || name == "to" => Ok(Token::IN),
+                    _ if name == "xor" => Ok(Token::XOR),
+                    _ if name == "del" => Ok(Token::DEL),
+                    // a currency code can be used like its symbol: 100 USD
+                    _ => match Currency::from_code(&name) {
+                        Some(currency) => Ok(Token::MONEY(currency)),
+                        None => Ok(Token::VAR(name))
+                    }
+                }
             },
-            _ => {Err(Error::InvalidSyntax)}
+            _ => {Err(Error::InvalidSyntax(self.pos))}
         }
     }
 }
@@ -233,16 +862,21 @@ impl Lexer {
 
 /// The parser consume the tokens and create an AST tree
 
+#[derive(Debug, Clone)]
 struct AST {
     token: Token,
-    children: Vec<AST>
+    children: Vec<AST>,
+    // Position (in chars) of the name of a function call, or of a bitwise operator,
+    // for the syntax errors of its arguments
+    pos: usize
 }
 
 impl AST {
     fn new(token: Token, children: Vec<AST>) -> AST {
         AST {
             token: token,
-            children: children
+            children: children,
+            pos: 0
         }
     }
 }
@@ -254,7 +888,7 @@ pub struct Parser {
 }
 
 impl Parser {
-    fn new(mut lexer: Lexer) -> Result<Parser, Error> {
+    pub fn new(mut lexer: Lexer) -> Result<Parser, Error> {
         let token = lexer.get_next_token()?;
 
         Ok(Parser {
@@ -269,10 +903,15 @@ impl Parser {
             self.current_token = self.lexer.get_next_token()?;
             Ok(())
         } else {
-            Err(Error::InvalidSyntax)
+            Err(self.syntax_error())
         }
     }
 
+    /// Return an InvalidSyntax error at the position of the current token
+    fn syntax_error(&self) -> Error {
+        Error::InvalidSyntax(self.lexer.token_start)
+    }
+
     /// number : INTEGER | FLOAT
     fn number(&mut self) -> Result<AST, Error> {
         let token = self.current_token.clone();
@@ -290,7 +929,7 @@ impl Parser {
                 let node = AST::new(token, vec![]);
                 Ok(node)
             },
-            _ => {Err(Error::InvalidSyntax)}
+            _ => {Err(self.syntax_error())}
         }
     }
 
@@ -323,18 +962,15 @@ impl Parser {
                     _ => {Ok(node)}
                 }
             },
-            _ => {Err(Error::InvalidSyntax)}
+            _ => {Err(self.syntax_error())}
         }
     }
 
-    /// factor : (PLUS | MINUS) factor | number | LPAREN expr RPAREN | VAR
+    /// factor : (PLUS | MINUS) factor | power
     fn factor(&mut self) -> Result<AST, Error> {
         let token = self.current_token.clone();
         
         match token {
-            Token::MONEY(_) | Token::INTEGER(_) | Token::FLOAT(_) => {
-                self.value()
-            },
             // (PLUS | MINUS) factor
             Token::PLUS | Token::MINUS=> {
                 match token {
@@ -346,10 +982,36 @@ impl Parser {
                 let node = AST::new(token, children); 
                 Ok(node)
             },
+            _ => self.power()
+        }
+    }
+
+    /// power : atom (POW factor)?
+    ///
+    /// The power binds tighter than the sign, `-2^2` is `-(2^2)`, but the exponent can have one: `2^-2`.
+    /// It's right associative, `2^3^2` is `2^(3^2)`.
+    fn power(&mut self) -> Result<AST, Error> {
+        let node = self.atom()?;
+
+        if self.current_token == Token::POW {
+            self.eat(Token::POW)?;
+            return Ok(AST::new(Token::POW, vec![node, self.factor()?]));
+        }
+        Ok(node)
+    }
+
+    /// atom : number | LPAREN expr RPAREN | VAR | function
+    fn atom(&mut self) -> Result<AST, Error> {
+        let token = self.current_token.clone();
+
+        match token {
+            Token::MONEY(_) | Token::INTEGER(_) | Token::FLOAT(_) => {
+                self.value()
+            },
             // LPAREN expr RPAREN
             Token::LPAREN => {
                 self.eat(Token::LPAREN)?;
-                let node = self.expr()?;
+                let node = self.expression()?;
                 self.eat(Token::RPAREN)?;
                 Ok(node)
             },
@@ -358,40 +1020,124 @@ impl Parser {
                 let node = AST::new(Token::VAR(name), vec![]);
                 Ok(node)
             },
+            Token::FUNC(_) => {
+                self.function()
+            },
+            Token::DATE(days) => {
+                self.eat(Token::DATE(days))?;
+                Ok(AST::new(token, vec![]))
+            },
+            Token::DURATION(seconds) => {
+                self.eat(Token::DURATION(seconds))?;
+                Ok(AST::new(token, vec![]))
+            },
+            Token::QUANTITY(value, unit) => {
+                self.eat(Token::QUANTITY(value, unit))?;
+                Ok(AST::new(token, vec![]))
+            },
+            Token::IMAGINARY(value) => {
+                self.eat(Token::IMAGINARY(value))?;
+                Ok(AST::new(token, vec![]))
+            },
             _ => {
-                Err(Error::InvalidSyntax)
+                Err(self.syntax_error())
             }
         }
     }
 
-    /// term : factor (VAR)* ((MUL | DIV) factor)*
-    ///      | factor (VAR)*            <-- implicit multiplication of variables. Like 4ab + 12 TODO
-    fn term(&mut self) -> Result<AST, Error> {
+    /// function : FUNC LPAREN (expr (COMMA expr)*)? RPAREN
+    fn function(&mut self) -> Result<AST, Error> {
+        let token = self.current_token.clone();
+        let pos = self.lexer.token_start;
+        self.eat(token.clone())?;
+
+        self.eat(Token::LPAREN)?;
+        // a function without arguments, like random()
+        if self.current_token == Token::RPAREN {
+            self.eat(Token::RPAREN)?;
+            return Ok(AST { pos, ..AST::new(token, vec![]) });
+        }
+        let mut arguments = vec![self.expression()?];
+
+        while self.current_token == Token::COMMA {
+            self.eat(Token::COMMA)?;
+            arguments.push(self.expression()?);
+        }
+        self.eat(Token::RPAREN)?;
+
+        Ok(AST { pos, ..AST::new(token, arguments) })
+    }
+
+    /// postfix : factor (FACT | PERCENT)*
+    ///
+    /// The factorial applies to the whole factor, so `-3!` is `(-3)!`.
+    fn postfix(&mut self) -> Result<AST, Error> {
         let mut node = self.factor()?;
 
-        while matches!(self.current_token, Token::VAR(_)) {
+        while self.current_token == Token::FACT || self.current_token == Token::PERCENT {
+            let token = self.current_token.clone();
+            self.eat(token.clone())?;
+            node = AST::new(token, vec![node]);
+        }
+        Ok(node)
+    }
+
+    /// term : postfix (VAR | postfix)* ((MUL | DIV | INTDIV | MOD) postfix)*
+    ///      | postfix (VAR)*            <-- implicit multiplication of variables. Like 4ab + 12
+    ///      | postfix (postfix)*        <-- implicit multiplication of parentheses. Like 2(3 + 4)
+    ///      | postfix OF postfix        <-- percentage of a value. Like 20% of 100
+    ///
+    /// `%` (MOD) is the remainder of the division, not a percentage.
+    /// The lexer gives us a PERCENT token for those.
+    fn term(&mut self) -> Result<AST, Error> {
+        let mut node = self.postfix()?;
+
+        // 20% of 100
+        if self.current_token == Token::OF {
+            if node.token != Token::PERCENT {
+                return Err(self.syntax_error());
+            }
+            self.eat(Token::OF)?;
+            node = AST::new(Token::OF, vec![node, self.postfix()?]);
+        }
+
+        while matches!(self.current_token, Token::VAR(_) | Token::LPAREN) {
             match self.current_token.clone() {
                 Token::VAR(name) => {
                     self.eat(Token::VAR(name.clone()))?;
                     let var_node = AST::new(Token::VAR(name.clone()), vec![]);
                     node = AST::new(Token::MUL, vec![node, var_node]);
                 },
+                // the postfix operators apply to the parenthesis only: 2(3)! is 2 * 6
+                Token::LPAREN => {
+                    node = AST::new(Token::MUL, vec![node, self.postfix()?]);
+                },
                 _ => {}
             }                
         }
 
-        while self.current_token == Token::MUL || self.current_token == Token::DIV {
+        while matches!(self.current_token, Token::MUL | Token::DIV | Token::INTDIV | Token::MOD) {
             
             match self.current_token {
                 Token::MUL => {
                     self.eat(Token::MUL)?;
-                    let children: Vec<AST> = vec![node, self.factor()?];
+                    let children: Vec<AST> = vec![node, self.postfix()?];
                     node = AST::new(Token::MUL, children);
                 },
                 Token::DIV => {
                     self.eat(Token::DIV)?;
-                    let children: Vec<AST> = vec![node, self.factor()?];
+                    let children: Vec<AST> = vec![node, self.postfix()?];
                     node = AST::new(Token::DIV, children);
+                },
+                Token::INTDIV => {
+                    self.eat(Token::INTDIV)?;
+                    let children: Vec<AST> = vec![node, self.postfix()?];
+                    node = AST::new(Token::INTDIV, children);
+                },
+                Token::MOD => {
+                    self.eat(Token::MOD)?;
+                    let children: Vec<AST> = vec![node, self.postfix()?];
+                    node = AST::new(Token::MOD, children);
                 }
                 _ => {panic!("Incorrect token in term()")}
             }
@@ -399,7 +1145,81 @@ impl Parser {
         Ok(node)
     }
 
-    /// expr    : term   ((PLUS | MINUS) term)*
+    /// The whole expression, with the operators of the lowest precedence
+    fn expression(&mut self) -> Result<AST, Error> {
+        self.ternary()
+    }
+
+    /// ternary : comparison (QUESTION ternary COLON ternary)?
+    ///
+    /// `1 < 2 ? 5 : 10`, the conditions can be nested on the right: `a ? 1 : b ? 2 : 3`
+    fn ternary(&mut self) -> Result<AST, Error> {
+        let condition = self.comparison()?;
+
+        if self.current_token == Token::QUESTION {
+            self.eat(Token::QUESTION)?;
+            let then = self.ternary()?;
+            self.eat(Token::COLON)?;
+            let otherwise = self.ternary()?;
+            return Ok(AST::new(Token::QUESTION, vec![condition, then, otherwise]));
+        }
+
+        Ok(condition)
+    }
+
+    /// comparison : bitwise ((LT | GT | LE | GE | EQ | NE) bitwise)?
+    ///
+    /// `3 < 5` is a boolean. The comparisons can't be chained.
+    fn comparison(&mut self) -> Result<AST, Error> {
+        let node = self.bitwise()?;
+
+        if matches!(self.current_token, Token::LT | Token::GT | Token::LE | Token::GE | Token::EQ | Token::NE) {
+            let token = self.current_token.clone();
+            self.eat(token.clone())?;
+            return Ok(AST::new(token, vec![node, self.bitwise()?]));
+        }
+
+        Ok(node)
+    }
+
+    /// bitwise : shift ((AND | OR | XOR) shift)*
+    ///
+    /// The bitwise operators on integers: `12 & 10`, `12 | 3`, `6 xor 3`.
+    fn bitwise(&mut self) -> Result<AST, Error> {
+        let mut node = self.shift()?;
+
+        while matches!(self.current_token, Token::AND | Token::OR | Token::XOR) {
+            let token = self.current_token.clone();
+            let pos = self.lexer.token_start;
+            self.eat(token.clone())?;
+            let children: Vec<AST> = vec![node, self.shift()?];
+            node = AST { pos, ..AST::new(token, children) };
+        }
+
+        Ok(node)
+    }
+
+    /// shift   : expr ((SHL | SHR) expr)*
+    ///
+    /// The bit shifts of integers: `1 << 4`, `256 >> 2`.
+    fn shift(&mut self) -> Result<AST, Error> {
+        let mut node = self.expr()?;
+
+        while matches!(self.current_token, Token::SHL | Token::SHR) {
+            let token = self.current_token.clone();
+            let pos = self.lexer.token_start;
+            self.eat(token.clone())?;
+            let children: Vec<AST> = vec![node, self.expr()?];
+            node = AST { pos, ..AST::new(token, children) };
+        }
+
+        Ok(node)
+    }
+
+    /// expr    : term   ((PLUS | MINUS) term)* (IN (MONEY | UNIT))?
+    ///
+    /// `10€ in $` and `10€ to $` convert the result to another currency,
+    /// `100cm in m` to another unit.
     fn expr(&mut self) -> Result<AST, Error> {
         let mut node = self.term()?;
 
@@ -420,6 +1240,28 @@ impl Parser {
             }
         }
 
+        if self.current_token == Token::IN {
+            self.eat(Token::IN)?;
+
+            match self.current_token {
+                Token::MONEY(currency) => {
+                    self.eat(Token::MONEY(currency))?;
+                    node = AST::new(Token::IN, vec![node, AST::new(Token::MONEY(currency), vec![])]);
+                },
+                // the unit names are variables for the lexer, and `in` is also a keyword: 1ft in in
+                Token::VAR(ref name) if Unit::from_name(name).is_some() => {
+                    let unit = Unit::from_name(name).unwrap();
+                    self.eat(self.current_token.clone())?;
+                    node = AST::new(Token::IN, vec![node, AST::new(Token::UNIT(unit), vec![])]);
+                },
+                Token::IN => {
+                    self.eat(Token::IN)?;
+                    node = AST::new(Token::IN, vec![node, AST::new(Token::UNIT(Unit::Inch), vec![])]);
+                },
+                _ => return Err(self.syntax_error())
+            }
+        }
+
         Ok (node)
     }
     
@@ -430,37 +1272,91 @@ impl Parser {
         let var_name = self.current_token.clone();    
         self.eat(var_name.clone())?;
         
-        self.eat(Token::ASSIGN)?; // `=`
+        // `=`, or `+=` `-=` `*=` `/=` that are short for `x = x + ...`
+        let operator = match self.current_token {
+            Token::PLUSASSIGN => Some(Token::PLUS),
+            Token::MINUSASSIGN => Some(Token::MINUS),
+            Token::MULASSIGN => Some(Token::MUL),
+            Token::DIVASSIGN => Some(Token::DIV),
+            _ => None
+        };
+        self.eat(self.current_token.clone())?;
+
+        let mut value = self.expression()?;
+        if let Some(operator) = operator {
+            value = AST::new(operator, vec![AST::new(var_name.clone(), vec![]), value]);
+        }
 
         let node = AST::new(
             Token::ASSIGN, vec![
                 AST::new(var_name, vec![]),
-                self.expr()?
+                value
             ]
         );
 
         Ok(node)
     }
     
-    /// statement   : expr | assignement
-    fn statement(&mut self) -> Result<AST, Error> {
-        match self.current_token {
+    /// deletion : DEL VAR
+    ///
+    /// `del x` removes the variable `x`
+    fn deletion(&mut self) -> Result<AST, Error> {
+        self.eat(Token::DEL)?;
+
+        match self.current_token.clone() {
+            Token::VAR(name) => {
+                self.eat(Token::VAR(name.clone()))?;
+                Ok(AST::new(Token::DEL, vec![AST::new(Token::VAR(name), vec![])]))
+            },
+            _ => Err(self.syntax_error())
+        }
+    }
+
+    /// statement   : expr | assignement | deletion
+    fn statement(&mut self) -> Result<AST, Error> {
+        match self.current_token {
+            Token::DEL => self.deletion(),
             Token::VAR(_) => {
                 let mut lex = self.lexer.clone();
-                if lex.get_next_token()? == Token::ASSIGN {
+                let next_token = lex.get_next_token()?;
+                if matches!(next_token, Token::ASSIGN | Token::PLUSASSIGN | Token::MINUSASSIGN | Token::MULASSIGN | Token::DIVASSIGN) {
                     self.assignement()
                 } else {
-                    self.expr()
+                    self.expression()
                 }
             },
-            _ => {self.expr()}
+            _ => {self.expression()}
         }
     }
 
 
+    /// program     : statement (SEMICOLON statement)* SEMICOLON?
+    ///
+    /// Several statements on one line: `a = 2; b = 3; a + b`
+    fn program(&mut self) -> Result<AST, Error> {
+        let mut statements = vec![self.statement()?];
+
+        while self.current_token == Token::SEMICOLON {
+            self.eat(Token::SEMICOLON)?;
+            if self.current_token == Token::EOF {
+                break;
+            }
+            statements.push(self.statement()?);
+        }
+
+        if self.current_token != Token::EOF {
+            return Err(self.syntax_error());
+        }
+
+        if statements.len() == 1 {
+            Ok(statements.remove(0))
+        } else {
+            Ok(AST::new(Token::SEMICOLON, statements))
+        }
+    }
+
     fn parse(&mut self) -> Result<AST, Error> {
-        //self.expr()
-        self.statement()
+        self.program()
     }
 }
 
@@ -470,57 +1366,251 @@ impl Parser {
 //#############################################################
 
 /// Result of parsing the AST
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum ResType {
     Int(i128),
     Float(f64),
-    Money(f64, Currency)
+    Money(f64, Currency),
+    Bool(bool),
+    /// A reduced fraction numerator/denominator, the denominator is greater than 1
+    Rational(i128, i128),
+    /// A day, as the number of days since 1970-01-01
+    Date(i64),
+    /// A duration in seconds
+    Duration(f64),
+    /// A measure in a unit, like 5 km
+    Quantity(f64, Unit),
+    /// A complex number with its real and imaginary parts, like 3+4i.
+    /// The imaginary part isn't 0, or it would be a real number.
+    Complex(f64, f64),
+    /// The variables listed by `vars`, sorted by name
+    Variables(Vec<(String, ResType)>),
+    /// An integer that doesn't fit in an i128
+    #[cfg(feature = "bigint")]
+    BigInt(BigInt)
 }
 
 impl ResType {
-    fn get_i128(self) -> i128 {
-        match self {
+    fn get_i128(&self) -> i128 {
+        match *self {
             ResType::Int(val) => {val},
             ResType::Float(val) => {val as i128}
             ResType::Money(val, _currency) => {val as i128}
+            ResType::Bool(val) => {val as i128}
+            ResType::Rational(num, den) => {num / den}
+            ResType::Date(days) => {days as i128}
+            ResType::Duration(seconds) => {seconds as i128}
+            ResType::Quantity(val, _unit) => {val as i128}
+            // the real part
+            ResType::Complex(re, _im) => {re as i128}
+            ResType::Variables(_) => {0}
+            #[cfg(feature = "bigint")]
+            ResType::BigInt(ref val) => {val.to_i128().unwrap_or(if val.sign() == Sign::Minus { i128::MIN } else { i128::MAX })}
         }
     }
     
-    fn get_f64(self) -> f64 {
-        match self {
+    fn get_f64(&self) -> f64 {
+        match *self {
             ResType::Float(val) => {val},
             ResType::Int(val) => {val as f64},
             ResType::Money(val, _currency) => {val},
+            ResType::Bool(val) => {val as i128 as f64},
+            ResType::Rational(num, den) => {num as f64 / den as f64},
+            ResType::Date(days) => {days as f64},
+            ResType::Duration(seconds) => {seconds},
+            ResType::Quantity(val, _unit) => {val},
+            ResType::Complex(re, _im) => {re},
+            ResType::Variables(_) => {f64::NAN},
+            #[cfg(feature = "bigint")]
+            ResType::BigInt(ref val) => {val.to_f64().unwrap_or(f64::NAN)},
         }
     }
 
-    fn get_currency(self) -> Option<Currency> {
-        match self {
+    fn get_currency(&self) -> Option<Currency> {
+        match *self {
             ResType::Money(_, currency) => {Some(currency)},
             _ => {None}
         }
     }
+
+    /// Compare the values of two numbers, the integers are compared exactly.
+    /// None if one of them is NaN or a boolean.
+    fn compare(&self, other: &ResType) -> Option<Ordering> {
+        match (self, other) {
+            (ResType::Int(left), ResType::Int(right)) => Some(left.cmp(right)),
+            (ResType::Date(left), ResType::Date(right)) => Some(left.cmp(right)),
+            (ResType::Duration(left), ResType::Duration(right)) => left.partial_cmp(right),
+            (ResType::Quantity(left, left_unit), ResType::Quantity(right, right_unit)) if left_unit == right_unit => {
+                left.partial_cmp(right)
+            },
+            // the booleans, dates, durations, quantities and complex numbers aren't ordered with the numbers
+            (ResType::Bool(_) | ResType::Date(_) | ResType::Duration(_) | ResType::Quantity(_, _) | ResType::Complex(_, _), _)
+                | (_, ResType::Bool(_) | ResType::Date(_) | ResType::Duration(_) | ResType::Quantity(_, _) | ResType::Complex(_, _)) => None,
+            #[cfg(feature = "bigint")]
+            (left, right) if left.is_integer() && right.is_integer() => Some(left.big_integer().cmp(&right.big_integer())),
+            _ => self.get_f64().partial_cmp(&other.get_f64())
+        }
+    }
+
+    /// The reduced fraction `num/den`, or an integer if it is `n/1`.
+    /// None if it doesn't fit in an i128, `den` must not be 0.
+    fn rational(num: i128, den: i128) -> Option<ResType> {
+        let divisor = gcd(num, den)?;
+        let (mut num, mut den) = (num / divisor, den / divisor);
+        if den < 0 {
+            num = num.checked_neg()?;
+            den = den.checked_neg()?;
+        }
+
+        if den == 1 {
+            Some(ResType::Int(num))
+        } else {
+            Some(ResType::Rational(num, den))
+        }
+    }
+
+    /// The complex number `re + im*i`, or a real number if `im` is 0: (1+i)*(1-i) is 2
+    fn complex(re: f64, im: f64) -> ResType {
+        if im != 0.0 {
+            return ResType::Complex(re, im);
+        }
+
+        if re.fract() == 0.0 && re.abs() < i128::MAX as f64 {
+            ResType::Int(re as i128)
+        } else {
+            ResType::Float(re)
+        }
+    }
+
+    /// The real and imaginary parts of a number, the imaginary part of a real number is 0
+    fn complex_parts(&self) -> (f64, f64) {
+        match *self {
+            ResType::Complex(re, im) => (re, im),
+            _ => (self.get_f64(), 0.0)
+        }
+    }
+
+    /// The numerator and denominator of an integer or a fraction
+    fn fraction(&self) -> Option<Fraction> {
+        match *self {
+            ResType::Rational(num, den) => Some((num, den)),
+            ResType::Int(val) => Some((val, 1)),
+            _ => None
+        }
+    }
+
+    /// Apply `op` to the fractions of two integers or fractions, like `fraction_add`.
+    /// None if they aren't fractions, or if the result doesn't fit in an i128.
+    fn fraction_op(&self, other: &ResType, op: fn(Fraction, Fraction) -> Option<Fraction>) -> Option<ResType> {
+        let (num, den) = op(self.fraction()?, other.fraction()?)?;
+        ResType::rational(num, den)
+    }
+
+    /// Apply an arithmetic operation that can't panic on an integer overflow.
+    ///
+    /// Two integers are computed with `int_op`, like `i128::checked_add`,
+    /// the other types with the operator trait `op`, like `ResType::add`.
+    ///
+    /// With the `bigint` feature, the integers that overflow are computed again as big integers.
+    fn checked(self, other: ResType, int_op: fn(i128, i128) -> Option<i128>, op: fn(ResType, ResType) -> ResType) -> Result<ResType, Error> {
+        match (self, other) {
+            (ResType::Int(left), ResType::Int(right)) => match int_op(left, right) {
+                Some(res) => Ok(ResType::Int(res)),
+                #[cfg(feature = "bigint")]
+                None => Ok(op(ResType::BigInt(BigInt::from(left)), ResType::BigInt(BigInt::from(right)))),
+                #[cfg(not(feature = "bigint"))]
+                None => Err(Error::Overflow)
+            },
+            (left, right) => Ok(op(left, right))
+        }
+    }
+}
+
+#[cfg(feature = "bigint")]
+impl ResType {
+    /// An Int, or a BigInt if `val` doesn't fit in an i128
+    fn big(val: BigInt) -> ResType {
+        match val.to_i128() {
+            Some(val) => ResType::Int(val),
+            None => ResType::BigInt(val)
+        }
+    }
+
+    fn is_integer(&self) -> bool {
+        matches!(self, ResType::Int(_) | ResType::BigInt(_))
+    }
+
+    fn big_integer(&self) -> BigInt {
+        match self {
+            ResType::BigInt(val) => val.clone(),
+            _ => BigInt::from(self.get_i128())
+        }
+    }
 }
 
+/// Greatest common divisor with the Euclidean algorithm, never negative: gcd(-4, 6) = 2.
+/// None if it doesn't fit in an i128, like gcd(i128::MIN, 0).
+fn gcd(a: i128, b: i128) -> Option<i128> {
+    let (mut a, mut b) = (a.unsigned_abs(), b.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    i128::try_from(a).ok()
+}
+
+/// Least common multiple, never negative: lcm(4, 6) = 12. None if it doesn't fit in an i128.
+fn lcm(a: i128, b: i128) -> Option<i128> {
+    if a == 0 || b == 0 {
+        return Some(0);
+    }
+    (a / gcd(a, b)?).checked_mul(b)?.checked_abs()
+}
+
+/// The numerator and denominator of a fraction
+type Fraction = (i128, i128);
+
+// a/b + c/d = (ad + cb) / bd, reduced by ResType::rational
+
+fn fraction_add((a, b): Fraction, (c, d): Fraction) -> Option<Fraction> {
+    Some((a.checked_mul(d)?.checked_add(c.checked_mul(b)?)?, b.checked_mul(d)?))
+}
+
+fn fraction_sub((a, b): Fraction, (c, d): Fraction) -> Option<Fraction> {
+    Some((a.checked_mul(d)?.checked_sub(c.checked_mul(b)?)?, b.checked_mul(d)?))
+}
+
+fn fraction_mul((a, b): Fraction, (c, d): Fraction) -> Option<Fraction> {
+    Some((a.checked_mul(c)?, b.checked_mul(d)?))
+}
+
+fn fraction_div((a, b): Fraction, (c, d): Fraction) -> Option<Fraction> {
+    Some((a.checked_mul(d)?, b.checked_mul(c)?))
+}
+
+/// Always positive like the integer remainder: a/b mod c/d = (ad mod cb) / bd
+fn fraction_rem((a, b): Fraction, (c, d): Fraction) -> Option<Fraction> {
+    Some((a.checked_mul(d)?.checked_rem_euclid(c.checked_mul(b)?)?, b.checked_mul(d)?))
+}
+
+// The fractions stay exact with the integers and other fractions,
+// they become floats if they overflow.
+
+// The arithmetic on Money keeps the currency of the left value, without converting
+// the right one. Use the Interpreter for that, it knows the conversion rates.
+
 impl Add for ResType {
     type Output = Self; 
     
     fn add(self, other: Self) -> ResType {
         match (self, other) {
-            
-            // Both numbers are of type Money
-            (left, right) if matches!(left, ResType::Money(_, _)) && matches!(right, ResType::Money(_, _)) => {
-                let currency_left = left.get_currency().unwrap();
-                let currency_right = right.get_currency().unwrap();
 
-                if currency_left != currency_right {
-                    panic!("We don't support conversions at the moment");
-                }
-                
-                ResType::Money(left.get_f64() + right.get_f64(), currency_left)
+            // One of them is a complex number
+            (left, right) if matches!(left, ResType::Complex(_, _)) || matches!(right, ResType::Complex(_, _)) => {
+                let ((a, b), (c, d)) = (left.complex_parts(), right.complex_parts());
+                ResType::complex(a + c, b + d)
             },
-            
-            // Left number is of type Money
+
+            // Left number is of type Money, or both are
             (left, right) if matches!(left, ResType::Money(_, _)) => {
                 let currency_left = left.get_currency().unwrap();
                 ResType::Money(left.get_f64() + right.get_f64(), currency_left)
@@ -536,9 +1626,18 @@ impl Add for ResType {
             (left_value, right_value) if matches!(left_value, ResType::Float(_)) || matches!(right_value, ResType::Float(_)) => {
                 ResType::Float(left_value.get_f64() + right_value.get_f64())
             },
+            // One of them is a fraction
+            (left_value, right_value) if matches!(left_value, ResType::Rational(_, _)) || matches!(right_value, ResType::Rational(_, _)) => {
+                left_value.fraction_op(&right_value, fraction_add)
+                    .unwrap_or(ResType::Float(left_value.get_f64() + right_value.get_f64()))
+            },
+            #[cfg(feature = "bigint")]
+            (left, right) if matches!(left, ResType::BigInt(_)) || matches!(right, ResType::BigInt(_)) => {
+                ResType::big(left.big_integer() + right.big_integer())
+            },
             // Both Integers
-            _ => {
-                ResType::Int(self.get_i128() + other.get_i128())
+            (left, right) => {
+                ResType::Int(left.get_i128() + right.get_i128())
             }
         }
     }
@@ -549,20 +1648,14 @@ impl Sub for ResType {
     
     fn sub(self, other: Self) -> ResType {
         match (self, other) {
-            
-            // Both numbers are of type Money
-            (left, right) if matches!(left, ResType::Money(_, _)) && matches!(right, ResType::Money(_, _)) => {
-                let currency_left = left.get_currency().unwrap();
-                let currency_right = right.get_currency().unwrap();
 
-                if currency_left != currency_right {
-                    panic!("We don't support conversions at the moment");
-                }
-                
-                ResType::Money(left.get_f64() - right.get_f64(), currency_left)
+            // One of them is a complex number
+            (left, right) if matches!(left, ResType::Complex(_, _)) || matches!(right, ResType::Complex(_, _)) => {
+                let ((a, b), (c, d)) = (left.complex_parts(), right.complex_parts());
+                ResType::complex(a - c, b - d)
             },
-            
-            // Left number is of type Money
+
+            // Left number is of type Money, or both are
             (left, right) if matches!(left, ResType::Money(_, _)) => {
                 let currency_left = left.get_currency().unwrap();
                 ResType::Money(left.get_f64() - right.get_f64(), currency_left)
@@ -578,9 +1671,18 @@ impl Sub for ResType {
             (left_value, right_value) if matches!(left_value, ResType::Float(_)) || matches!(right_value, ResType::Float(_)) => {
                 ResType::Float(left_value.get_f64() - right_value.get_f64())
             },
+            // One of them is a fraction
+            (left_value, right_value) if matches!(left_value, ResType::Rational(_, _)) || matches!(right_value, ResType::Rational(_, _)) => {
+                left_value.fraction_op(&right_value, fraction_sub)
+                    .unwrap_or(ResType::Float(left_value.get_f64() - right_value.get_f64()))
+            },
+            #[cfg(feature = "bigint")]
+            (left, right) if matches!(left, ResType::BigInt(_)) || matches!(right, ResType::BigInt(_)) => {
+                ResType::big(left.big_integer() - right.big_integer())
+            },
             // Both Integers
-            _ => {
-                ResType::Int(self.get_i128() - other.get_i128())
+            (left, right) => {
+                ResType::Int(left.get_i128() - right.get_i128())
             }
         }
     }
@@ -591,20 +1693,14 @@ impl Mul for ResType {
     
     fn mul(self, other: Self) -> ResType {
         match (self, other) {
-            
-            // Both numbers are of type Money
-            (left, right) if matches!(left, ResType::Money(_, _)) && matches!(right, ResType::Money(_, _)) => {
-                let currency_left = left.get_currency().unwrap();
-                let currency_right = right.get_currency().unwrap();
 
-                if currency_left != currency_right {
-                    panic!("We don't support conversions at the moment");
-                }
-                
-                ResType::Money(left.get_f64() * right.get_f64(), currency_left)
+            // One of them is a complex number: (a+bi)(c+di) = (ac-bd) + (ad+bc)i
+            (left, right) if matches!(left, ResType::Complex(_, _)) || matches!(right, ResType::Complex(_, _)) => {
+                let ((a, b), (c, d)) = (left.complex_parts(), right.complex_parts());
+                ResType::complex(a * c - b * d, a * d + b * c)
             },
-            
-            // Left number is of type Money
+
+            // Left number is of type Money, or both are
             (left, right) if matches!(left, ResType::Money(_, _)) => {
                 let currency_left = left.get_currency().unwrap();
                 ResType::Money(left.get_f64() * right.get_f64(), currency_left)
@@ -620,9 +1716,18 @@ impl Mul for ResType {
             (left_value, right_value) if matches!(left_value, ResType::Float(_)) || matches!(right_value, ResType::Float(_)) => {
                 ResType::Float(left_value.get_f64() * right_value.get_f64())
             },
+            // One of them is a fraction
+            (left_value, right_value) if matches!(left_value, ResType::Rational(_, _)) || matches!(right_value, ResType::Rational(_, _)) => {
+                left_value.fraction_op(&right_value, fraction_mul)
+                    .unwrap_or(ResType::Float(left_value.get_f64() * right_value.get_f64()))
+            },
+            #[cfg(feature = "bigint")]
+            (left, right) if matches!(left, ResType::BigInt(_)) || matches!(right, ResType::BigInt(_)) => {
+                ResType::big(left.big_integer() * right.big_integer())
+            },
             // Both Integers
-            _ => {
-                ResType::Int(self.get_i128() * other.get_i128())
+            (left, right) => {
+                ResType::Int(left.get_i128() * right.get_i128())
             }
         }
     }
@@ -633,20 +1738,15 @@ impl Div for ResType {
     
     fn div(self, other: Self) -> ResType {
         match (self, other) {
-            
-            // Both numbers are of type Money
-            (left, right) if matches!(left, ResType::Money(_, _)) && matches!(right, ResType::Money(_, _)) => {
-                let currency_left = left.get_currency().unwrap();
-                let currency_right = right.get_currency().unwrap();
 
-                if currency_left != currency_right {
-                    panic!("We don't support conversions at the moment");
-                }
-                
-                ResType::Money(left.get_f64() / right.get_f64(), currency_left)
+            // One of them is a complex number: (a+bi)/(c+di) = ((ac+bd) + (bc-ad)i) / (c²+d²)
+            (left, right) if matches!(left, ResType::Complex(_, _)) || matches!(right, ResType::Complex(_, _)) => {
+                let ((a, b), (c, d)) = (left.complex_parts(), right.complex_parts());
+                let divisor = c * c + d * d;
+                ResType::complex((a * c + b * d) / divisor, (b * c - a * d) / divisor)
             },
-            
-            // Left number is of type Money
+
+            // Left number is of type Money, or both are
             (left, right) if matches!(left, ResType::Money(_, _)) => {
                 let currency_left = left.get_currency().unwrap();
                 ResType::Money(left.get_f64() / right.get_f64(), currency_left)
@@ -663,24 +1763,82 @@ impl Div for ResType {
                 ResType::Float(left_value.get_f64() / right_value.get_f64())
             },
 
+            // One of them is a fraction
+            (left_value, right_value) if matches!(left_value, ResType::Rational(_, _)) || matches!(right_value, ResType::Rational(_, _)) => {
+                left_value.fraction_op(&right_value, fraction_div)
+                    .unwrap_or(ResType::Float(left_value.get_f64() / right_value.get_f64()))
+            },
+
+            #[cfg(feature = "bigint")]
+            (left, right) if matches!(left, ResType::BigInt(_)) || matches!(right, ResType::BigInt(_)) => {
+                let (left_val, right_val) = (left.big_integer(), right.big_integer());
+                if (&left_val % &right_val).is_zero() {
+                    ResType::big(left_val / right_val)
+                } else {
+                    ResType::Float(left.get_f64() / right.get_f64())
+                }
+            },
+
             // Both are Integers
-            _ => {
-                let left_val = self.get_i128();
-                let right_val = other.get_i128();
+            (left, right) => {
+                let left_val = left.get_i128();
+                let right_val = right.get_i128();
 
                 // If the divison returns a round value give an Integer
                 if left_val % right_val == 0 {
-                    ResType::Int(self.get_i128() / other.get_i128())
+                    ResType::Int(left_val / right_val)
 
                 // Otherwise, we return a Float
                 } else {
-                    ResType::Float(self.get_f64() / other.get_f64())
+                    ResType::Float(left.get_f64() / right.get_f64())
                 }
             }
         }
     }
 }
 
+impl Rem for ResType {
+    type Output = Self; 
+    
+    fn rem(self, other: Self) -> ResType {
+        match (self, other) {
+
+            // Left number is of type Money, or both are
+            (left, right) if matches!(left, ResType::Money(_, _)) => {
+                let currency_left = left.get_currency().unwrap();
+                ResType::Money(left.get_f64().rem_euclid(right.get_f64()), currency_left)
+            }
+
+            // Right number is of type Money
+            (left, right) if matches!(right, ResType::Money(_, _)) => {
+                let currency_left = right.get_currency().unwrap();
+                ResType::Money(left.get_f64().rem_euclid(right.get_f64()), currency_left)
+            }
+
+            // One of the types is Float
+            (left_value, right_value) if matches!(left_value, ResType::Float(_)) || matches!(right_value, ResType::Float(_)) => {
+                ResType::Float(left_value.get_f64().rem_euclid(right_value.get_f64()))
+            },
+
+            // One of them is a fraction
+            (left_value, right_value) if matches!(left_value, ResType::Rational(_, _)) || matches!(right_value, ResType::Rational(_, _)) => {
+                left_value.fraction_op(&right_value, fraction_rem)
+                    .unwrap_or(ResType::Float(left_value.get_f64().rem_euclid(right_value.get_f64())))
+            },
+
+            #[cfg(feature = "bigint")]
+            (left, right) if matches!(left, ResType::BigInt(_)) || matches!(right, ResType::BigInt(_)) => {
+                ResType::big(left.big_integer().rem_euclid(&right.big_integer()))
+            },
+
+            // Both are Integers, the result is always positive: -7 % 3 = 2
+            (left, right) => {
+                ResType::Int(left.get_i128().rem_euclid(right.get_i128()))
+            }
+        }
+    }
+}
+
 impl Neg for ResType {
     type Output = Self; 
     
@@ -689,381 +1847,3537 @@ impl Neg for ResType {
             ResType::Int(val) => ResType::Int(-val),
             ResType::Float(val) => ResType::Float(-val),
             ResType::Money(val, currency) => ResType::Money(-val, currency),
+            ResType::Bool(val) => ResType::Bool(!val),
+            ResType::Rational(num, den) => ResType::Rational(-num, den),
+            ResType::Date(days) => ResType::Date(-days),
+            ResType::Duration(seconds) => ResType::Duration(-seconds),
+            ResType::Quantity(val, unit) => ResType::Quantity(-val, unit),
+            ResType::Complex(re, im) => ResType::Complex(-re, -im),
+            ResType::Variables(_) => self,
+            #[cfg(feature = "bigint")]
+            ResType::BigInt(val) => ResType::big(-val),
         }        
     }
 }
 
-impl fmt::Display for ResType {
-
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            ResType::Int(val)  => {write!(f, "{}", val)},
-            ResType::Float(val) => {write!(f, "{:?}", val)},
-            ResType::Money(val, currency) => {
-                write!(f, "{:.2} {}", val, currency)
-            },
-        }
-    }
+/// How the results are displayed
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct FormatOptions {
+    /// Number of decimals of the Float and Money results.
+    /// By default the Floats are as precise as needed, and the Money has the decimals of its currency.
+    pub precision: Option<usize>,
+    /// Separator between the groups of thousands, like `,` for `1,000,000`. None by default.
+    pub thousands_separator: Option<char>,
+    /// How the negative amounts of money are written
+    pub negative_money: NegativeMoney,
+    /// Write every currency symbol on this side of the amount.
+    /// None by default, each currency has its usual placement.
+    pub symbol_placement: Option<SymbolPlacement>,
+    /// The smallest and the biggest decimal exponents of the Floats written in full,
+    /// the others are in scientific notation like `1.0e20`. None by default, for `(-5, 15)`.
+    pub scientific_bounds: Option<(i32, i32)>
 }
 
-//#############################################################
-//   Interpreter
-//#############################################################
+/// Write `val` in scientific notation, with a decimal point in the mantissa like `1.0e20`
+fn scientific(val: f64, precision: Option<usize>) -> String {
+    let number = match precision {
+        Some(precision) => format!("{:.*e}", precision, val),
+        None => format!("{:e}", val)
+    };
 
-pub struct Interpreter {
-    parser: Parser,
-    variables: Rc<RefCell<HashMap<String, ResType>>>
+    match number.split_once('e') {
+        Some((mantissa, exponent)) if !mantissa.contains('.') && precision.is_none() => format!("{}.0e{}", mantissa, exponent),
+        _ => number
+    }
 }
 
-impl Interpreter {
-    fn new(parser: Parser, variables: Rc<RefCell<HashMap<String, ResType>>>) -> Interpreter {
-        Interpreter {
-            parser: parser,
-            variables: variables
-        }
-    }
+/// The style of the negative amounts of money
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub enum NegativeMoney {
+    /// `-500.00 €`
+    #[default]
+    Minus,
+    /// `(500.00 €)`, like in accounting
+    Parentheses
+}
 
-    fn visit_num(&self, node: &AST) -> ResType {
-        match node.token {
-            Token::INTEGER(i) => ResType::Int(i),
-            Token::FLOAT(f) => ResType::Float(f),
-            _ => panic!("Error: end node is not an integer")
+/// Insert the `separator` between the groups of thousands of the integer part of `number`
+fn group_thousands(number: String, separator: Option<char>) -> String {
+    let Some(separator) = separator else {
+        return number
+    };
+
+    let start = if number.starts_with('-') { 1 } else { 0 };
+    let end = number[start..].find(|c: char| !c.is_ascii_digit())
+        .map_or(number.len(), |len| start + len);
+    let digits = &number[start..end];
+
+    let mut grouped = String::from(&number[..start]);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(separator);
         }
+        grouped.push(digit);
     }
+    // the fractional digits aren't grouped
+    grouped.push_str(&number[end..]);
 
-    fn visit_variable(&self, node: &AST) -> Result<ResType, Error> {
-        match &node.token {
-            Token::VAR(var_name) => {
-                let var_list = self.variables.borrow();
-
-                match var_list.get(var_name) {
-                    Some(val) => return Ok(*val),
-                    None => {}
-                };
-
-                // if variable ends with an 's', we check if the singular is a variable
-                if let Some(last_char) = var_name.chars().nth(var_name.len()-1) {
-                    
-                    if last_char == 's' {
-                        let singular_varname: String = var_name.chars().take(var_name.len()-1).collect();
+    grouped
+}
 
-                        match var_list.get(&singular_varname) {
-                            Some(val) => return Ok(*val),
-                            _ => {}
-                        }
-                    }
+impl ResType {
+    /// Format the result according to the `options`
+    pub fn format(&self, options: &FormatOptions) -> String {
+        let number = match (self, options.precision) {
+            (ResType::Int(val), _) => format!("{}", val),
+            (ResType::Float(val), precision) if *val != 0.0 && val.is_finite() => {
+                let (min, max) = options.scientific_bounds.unwrap_or((-5, 15));
+                let exponent = val.abs().log10().floor() as i32;
+
+                if exponent < min || exponent > max {
+                    scientific(*val, precision)
+                } else if let Some(precision) = precision {
+                    format!("{:.*}", precision, val)
+                } else {
+                    format!("{:?}", val)
                 }
-                
-                Err(Error::UndefinedVariable)
             },
-            _ => panic!("Token is not a variable")
-        }
-    }
-
-    fn visit_binop(&mut self, node: &AST) -> Result<ResType, Error> {
-        let left_val = self.visit(&node.children[0])?;
-        let right_val = self.visit(&node.children[1])?;
-
-        match node.token {
-            Token::PLUS => {
-                Ok(left_val + right_val)
+            (ResType::Float(val), None) => format!("{:?}", val),
+            (ResType::Float(val), Some(precision)) => format!("{:.*}", precision, val),
+            (ResType::Money(val, currency), precision) => {
+                let precision = precision.unwrap_or(currency.decimals());
+                // the parentheses replace the minus
+                let val = if options.negative_money == NegativeMoney::Parentheses { val.abs() } else { *val };
+                format!("{:.*}", precision, val)
             },
-            Token::MINUS => {
-                Ok(left_val - right_val)
+            (ResType::Bool(val), _) => return format!("{}", val),
+            (ResType::Date(days), _) => {
+                let (year, month, day) = date_from_days(*days);
+                return format!("{:04}-{:02}-{:02}", year, month, day)
             },
-            Token::MUL => {
-                Ok(left_val * right_val)
+            (ResType::Duration(seconds), precision) => {
+                let (unit, value) = duration_unit(*seconds);
+                let number = match precision {
+                    Some(precision) => format!("{:.*}", precision, value),
+                    None => format!("{}", value)
+                };
+                return group_thousands(number, options.thousands_separator) + unit
             },
-            Token::DIV => {
-                // Let's catch division by zero before the happend
-                // because there is no checked_div function for f64.
-                
-                match right_val {
-                    ResType::Int(0) => return Err(Error::DivisonByZero),
-                    ResType::Float(val) => {
-                        if val == 0.0 {return Err(Error::DivisonByZero)}},
-                    _ => {}
+            (ResType::Quantity(val, _), None) => format!("{}", val),
+            (ResType::Quantity(val, _), Some(precision)) => format!("{:.*}", precision, val),
+            #[cfg(feature = "bigint")]
+            (ResType::BigInt(val), _) => format!("{}", val),
+            (ResType::Complex(re, im), _) => {
+                // the parts are formatted like the real numbers, 3+4i and not 3.0+4.0i
+                let part = |val: f64| ResType::complex(val, 0.0).format(options);
+                let imaginary = format!("{}i", part(im.abs()));
+
+                return match (*re == 0.0, *im < 0.0) {
+                    (true, false) => imaginary,
+                    (true, true) => format!("-{}", imaginary),
+                    (false, false) => format!("{}+{}", part(*re), imaginary),
+                    (false, true) => format!("{}-{}", part(*re), imaginary)
+                }
+            },
+            (ResType::Rational(num, den), _) => {
+                let num = group_thousands(format!("{}", num), options.thousands_separator);
+                let den = group_thousands(format!("{}", den), options.thousands_separator);
+                return format!("{}/{}", num, den)
+            },
+            (ResType::Variables(variables), _) if variables.is_empty() => return String::from("No variables"),
+            // a = 1, b = 2
+            (ResType::Variables(variables), _) => return variables.iter()
+                .map(|(name, value)| format!("{} = {}", name, value.format(options)))
+                .collect::<Vec<String>>()
+                .join(", "),
+        };
+        let number = group_thousands(number, options.thousands_separator);
+
+        match self {
+            ResType::Money(val, currency) => {
+                let amount = match options.symbol_placement.unwrap_or(currency.symbol_placement()) {
+                    // the minus stays before the symbol: -$5.00
+                    SymbolPlacement::Before => match number.strip_prefix('-') {
+                        Some(number) => format!("-{}{}", currency, number),
+                        None => format!("{}{}", currency, number)
+                    },
+                    SymbolPlacement::After => format!("{} {}", number, currency)
                 };
 
-                // Division has been implemented as a trait for ResType
-                let res = left_val / right_val;
-                Ok(res)
+                if *val < 0.0 && options.negative_money == NegativeMoney::Parentheses {
+                    format!("({})", amount)
+                } else {
+                    amount
+                }
             },
-            _ => panic!("Unkown BinOp Token in the AST")
+            ResType::Quantity(_, unit) => format!("{} {}", number, unit),
+            _ => number
         }
     }
+}
 
-    fn visit_unaryop(&mut self, node: &AST) -> Result<ResType, Error> {
-        let val = self.visit(&node.children[0])?;
-
-        match &node.token {
-            Token::PLUS  => {  Ok(val) },
-            Token::MINUS => { Ok(-val) },
-            Token::MONEY(currency) => {
-                let number = self.visit(&node.children[0])?;
-
-                match number {
-                    ResType::Int(val) => {
-                        Ok(ResType::Money(val as f64, *currency))
-                    },
-                    ResType::Float(val) => {
-                        Ok(ResType::Money(val, *currency))
-                    },
-                    _ => panic!("Unknown number type in Money creation")
-                }
+impl fmt::Display for ResType {
 
-            }
-            _ => {panic!("Invalid token type for an unary node")}
-        }
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.format(&FormatOptions::default()))
     }
+}
 
-    fn visit_assign(&mut self, node: &AST) -> Result<ResType, Error> {
-        let right_val = self.visit(&node.children[1])?;
+//#############################################################
+//   Interpreter
+//#############################################################
 
-        match &node.children[0].token {
-            Token::VAR(var_name) => {
-                let mut var = self.variables.borrow_mut();
-                var.insert(var_name.clone(), right_val);
-                // self.variables.set(insert(var_name.clone(), right_val));
-            },
-            _ => panic!("Assignement without a variable")
-        }
-        Ok(right_val)
+/// The arithmetic of the complex numbers, with the integer powers: i^2 = -1.
+/// The complex money, dates, durations and quantities aren't supported.
+fn complex_arithmetic(token: &Token, left: ResType, right: ResType) -> Result<ResType, Error> {
+    let is_number = |val: &ResType| !matches!(val, ResType::Money(_, _) | ResType::Date(_) | ResType::Duration(_) | ResType::Quantity(_, _));
+    if !is_number(&left) || !is_number(&right) {
+        return Err(Error::OutOfDomain);
     }
 
-    fn visit(&mut self, node: &AST) -> Result<ResType, Error> {
-        match node.token {
-            Token::INTEGER(_) | Token::FLOAT(_) => {
-                Ok(self.visit_num(node))
+    match token {
+        Token::PLUS => Ok(left + right),
+        Token::MINUS => Ok(left - right),
+        Token::MUL => Ok(left * right),
+        Token::DIV => {
+            if right.complex_parts() == (0.0, 0.0) {
+                return Err(Error::DivisonByZero);
+            }
+            Ok(left / right)
+        },
+        Token::POW => {
+            let ResType::Int(exponent) = right else {
+                return Err(Error::OutOfDomain)
+            };
+            if exponent < 0 && left.complex_parts() == (0.0, 0.0) {
+                return Err(Error::DivisonByZero);
+            }
+
+            // by squaring, the exponent is halved at each step
+            let (mut res, mut base, mut n) = (ResType::Int(1), left, exponent.unsigned_abs());
+            while n > 0 {
+                if n % 2 == 1 {
+                    res = res * base.clone();
+                }
+                base = base.clone() * base;
+                n /= 2;
+            }
+
+            Ok(if exponent < 0 { ResType::Int(1) / res } else { res })
+        },
+        _ => Err(Error::OutOfDomain)
+    }
+}
+
+/// The biggest number whose factorial is computed
+const MAX_FACTORIAL: i128 = 10_000;
+
+/// Return the value of a built-in constant like `pi` or `e`
+fn constant(name: &str) -> Option<f64> {
+    match name {
+        "pi" | "π" => Some(f64::consts::PI),
+        "e" => Some(f64::consts::E),
+        _ => None
+    }
+}
+
+/// The number of chars to insert, delete or replace to change `a` into `b`
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // distances between the start of `a` and each start of `b`
+    let mut distances: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut previous = distances[0];
+        distances[0] = i + 1;
+
+        for (j, &b_char) in b.iter().enumerate() {
+            let replaced = previous + usize::from(a_char != b_char);
+            previous = distances[j + 1];
+            distances[j + 1] = replaced.min(distances[j] + 1).min(previous + 1);
+        }
+    }
+
+    distances[b.len()]
+}
+
+/// The name closest to `name` among the `names`, if it differs by at most two chars
+/// and isn't a completely different name, like `y` for `x`.
+/// The variables of the lines like `ans` aren't the ones the user meant.
+fn closest_name<'a>(name: &str, names: impl Iterator<Item = &'a String>) -> Option<String> {
+    names.filter(|candidate| !is_line_variable(candidate))
+        .map(|candidate| (levenshtein(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= 2 && distance < name.chars().count())
+        .min()
+        .map(|(_, candidate)| candidate.clone())
+}
+
+/// Round `value` with `round`, like `f64::floor`.
+/// The money keeps its currency, the other numbers become integers when they fit in an i128.
+fn rounded(value: ResType, round: fn(f64) -> f64) -> ResType {
+    match value {
+        ResType::Int(_) | ResType::Bool(_) | ResType::Date(_) | ResType::Duration(_) | ResType::Variables(_) => value,
+        #[cfg(feature = "bigint")]
+        ResType::BigInt(_) => value,
+        ResType::Money(val, currency) => ResType::Money(round(val), currency),
+        ResType::Quantity(val, unit) => ResType::Quantity(round(val), unit),
+        ResType::Complex(re, im) => ResType::complex(round(re), round(im)),
+        ResType::Float(_) | ResType::Rational(_, _) => {
+            let res = round(value.get_f64());
+            if res.is_finite() && res.abs() < i128::MAX as f64 {
+                ResType::Int(res as i128)
+            } else {
+                ResType::Float(res)
+            }
+        }
+    }
+}
+
+/// The `n`th root `res` of `value`, as an integer when `value` is an integer with an exact root:
+/// root(16, 4) is 2, even if the float computation gives 1.9999999999999998
+fn integer_root(value: &ResType, res: f64, n: f64) -> ResType {
+    if let ResType::Int(val) = value {
+        let root = res.round() as i128;
+        if n > 0.0 && n.fract() == 0.0 && n <= u32::MAX as f64 && root.checked_pow(n as u32) == Some(*val) {
+            return ResType::Int(root);
+        }
+    }
+    ResType::Float(res)
+}
+
+/// Number of days since 1970-01-01 of a date, None if the date doesn't exist like 2024-02-30
+fn days_from_date(year: i64, month: u32, day: u32) -> Option<i64> {
+    let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+    let month_days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return None
+    };
+    if day == 0 || day > month_days {
+        return None;
+    }
+
+    // the years start in March, so the leap day is the last day of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year as i64;
+
+    Some(era * 146097 + day_of_era - 719468)
+}
+
+/// The year, month and day of a number of days since 1970-01-01
+fn date_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = era * 400 + year_of_era + if month <= 2 { 1 } else { 0 };
+
+    (year, month, day)
+}
+
+/// Convert a quantity to another `unit` of the same dimension, like 100cm to 1 m.
+/// With `binary`, 1KB is 1024 bytes.
+fn convert_unit(value: &ResType, unit: Unit, binary: bool) -> Result<ResType, Error> {
+    match value {
+        ResType::Quantity(_, from) if from.dimension() != unit.dimension() => Err(Error::IncompatibleUnits),
+        // 0C is 32F: the temperatures have an offset, they go through the Celsius degrees.
+        // The result is rounded to 10 decimals to hide the float errors: -246.3C is 26.85K
+        ResType::Quantity(val, from) if from.dimension() == Dimension::Temperature => {
+            above_absolute_zero(value.clone())?;
+            let celsius = from.celsius(*val);
+            let res = (unit.temperature(celsius) * 1e10).round() / 1e10;
+            Ok(ResType::Quantity(res, unit))
+        },
+        ResType::Quantity(val, from) => Ok(ResType::Quantity(val * from.factor(binary) / unit.factor(binary), unit)),
+        _ => Err(Error::IncompatibleUnits)
+    }
+}
+
+/// The `value`, or OutOfDomain if it's a temperature colder than 0K, like -300C
+fn above_absolute_zero(value: ResType) -> Result<ResType, Error> {
+    match value {
+        ResType::Quantity(val, unit) if unit.dimension() == Dimension::Temperature && unit.celsius(val) < Unit::Kelvin.celsius(0.0) => {
+            Err(Error::OutOfDomain)
+        },
+        _ => Ok(value)
+    }
+}
+
+/// Write a data size in the biggest unit it has at least one of: 1000MB is 1 GB, and 1024MiB is 1 GiB.
+fn scaled_data_size(value: ResType, binary: bool) -> ResType {
+    let ResType::Quantity(val, unit) = value else {
+        return value
+    };
+    if unit.dimension() != Dimension::Data {
+        return value;
+    }
+    let sizes = if Unit::BINARY_SIZES[1..].contains(&unit) { Unit::BINARY_SIZES } else { Unit::DECIMAL_SIZES };
+
+    let bytes = val * unit.factor(binary);
+    let unit = sizes.into_iter().rev()
+        .find(|size| bytes.abs() >= size.factor(binary))
+        .unwrap_or(Unit::Byte);
+    ResType::Quantity(bytes / unit.factor(binary), unit)
+}
+
+/// The unit used to display a duration, and the duration in this unit.
+/// The biggest unit that gives at most two decimals: 90min is 1.5h, but 100min stays 100min.
+fn duration_unit(seconds: f64) -> (&'static str, f64) {
+    let mut units = DURATION_UNITS.iter().filter(|(_, unit)| seconds.abs() >= *unit);
+
+    for &(name, unit) in units.clone() {
+        let value = seconds / unit;
+        if ((value * 100.0).round() - value * 100.0).abs() < 1e-9 {
+            return (name, value);
+        }
+    }
+
+    // the smallest unit, or seconds for the durations under one second
+    let (name, unit) = units.next_back().copied().unwrap_or(("s", 1.0));
+    (name, seconds / unit)
+}
+
+/// Number of ways to order `k` items out of `n`, with 0 <= k <= n. None if it doesn't fit in an i128.
+fn permutations(n: i128, k: i128) -> Option<i128> {
+    (n - k + 1..=n).try_fold(1_i128, |res, i| res.checked_mul(i))
+}
+
+/// Number of ways to choose `k` items out of `n`, with 0 <= k <= n. None if it doesn't fit in an i128.
+fn combinations(n: i128, k: i128) -> Option<i128> {
+    // C(n, k) = C(n, n - k), with fewer multiplications
+    let k = k.min(n - k);
+    // each step is C(n - k + i, i), always an integer
+    (1..=k).try_fold(1_i128, |res, i| Some(res.checked_mul(n - k + i)? / i))
+}
+
+/// Unit of the angles given to the trigonometric functions
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum AngleMode {
+    Radians,
+    Degrees
+}
+
+/// Convert `value` to the `to` currency. 1 `from` is worth `rates[(from, to)]` `to`,
+/// the inverse rate is used if only `(to, from)` is known.
+fn convert(value: ResType, to: Currency, rates: &HashMap<(Currency, Currency), f64>) -> Result<ResType, Error> {
+    match value {
+        ResType::Money(val, from) if from == to => Ok(ResType::Money(val, to)),
+        ResType::Money(val, from) => {
+            if let Some(rate) = rates.get(&(from, to)) {
+                Ok(ResType::Money(val * rate, to))
+            } else if let Some(rate) = rates.get(&(to, from)) {
+                Ok(ResType::Money(val / rate, to))
+            } else {
+                Err(Error::NoConversionRate)
+            }
+        },
+        _ => Ok(ResType::Money(value.get_f64(), to))
+    }
+}
+
+pub struct Interpreter {
+    parser: Parser,
+    variables: Rc<RefCell<HashMap<String, ResType>>>,
+    angle_mode: AngleMode,
+    rates: HashMap<(Currency, Currency), f64>,
+    /// Use the singular variable for a plural name, like `adulte` for `adultes`
+    plurals: bool,
+    /// The assignments that couldn't be computed yet, they are computed when the variable is used
+    definitions: Rc<RefCell<HashMap<String, Definition>>>,
+    /// The variables being computed, to detect the circular references
+    resolving: Vec<String>,
+    /// The division of integers gives an exact fraction like `1/3` instead of a float
+    rational: bool,
+    /// 1KB is 1024 bytes instead of 1000 bytes
+    binary_sizes: bool,
+    /// `Rate` and `rate` are the same variable
+    case_insensitive: bool,
+    /// The assignments can't shadow the built-in constants like `pi`
+    protect_constants: bool,
+    /// The currency of the operations between two currencies, instead of the currency of the left amount
+    default_currency: Option<Currency>,
+    /// The rounding of the money multiplied or divided
+    money_rounding: MoneyRounding
+}
+
+/// The expression assigned to a variable, computed each time the variable is used
+#[derive(Debug, Clone)]
+pub struct Definition(AST);
+
+impl Interpreter {
+    pub fn new(parser: Parser, variables: Rc<RefCell<HashMap<String, ResType>>>) -> Interpreter {
+        Interpreter {
+            parser: parser,
+            variables: variables,
+            angle_mode: AngleMode::Radians,
+            rates: HashMap::new(),
+            plurals: true,
+            definitions: Rc::new(RefCell::new(HashMap::new())),
+            resolving: Vec::new(),
+            rational: false,
+            binary_sizes: false,
+            case_insensitive: false,
+            protect_constants: false,
+            default_currency: None,
+            money_rounding: MoneyRounding::Exact
+        }
+    }
+
+    /// Give the angles of the trigonometric functions in radians or in degrees. In radians by default.
+    pub fn set_angle_mode(&mut self, angle_mode: AngleMode) {
+        self.angle_mode = angle_mode;
+    }
+
+    /// Register a conversion rate: 1 `from` is worth `rate` `to`.
+    ///
+    /// The inverse conversion is deduced from it, no need to register it as well.
+    pub fn add_rate(&mut self, from: Currency, to: Currency, rate: f64) {
+        self.rates.insert((from, to), rate);
+    }
+
+    /// Enable or disable the plural names, like `2 adultes` when `adulte` is a variable.
+    /// They are enabled by default.
+    pub fn set_plurals(&mut self, plurals: bool) {
+        self.plurals = plurals;
+    }
+
+    /// Keep the divisions of integers exact, `1/3` is the fraction 1/3 instead of 0.3333333333333333.
+    /// Disabled by default.
+    pub fn set_rational(&mut self, rational: bool) {
+        self.rational = rational;
+    }
+
+    /// Use the binary meaning of the decimal prefixes, 1KB is 1024 bytes like 1KiB instead of 1000 bytes.
+    /// Disabled by default.
+    pub fn set_binary_sizes(&mut self, binary_sizes: bool) {
+        self.binary_sizes = binary_sizes;
+    }
+
+    /// Ignore the case of the variable names, `Rate` is the variable `rate`.
+    /// Disabled by default.
+    pub fn set_case_insensitive(&mut self, case_insensitive: bool) {
+        self.case_insensitive = case_insensitive;
+    }
+
+    /// Refuse the assignments to the built-in constants like `pi` or `e`, instead of shadowing them.
+    /// Disabled by default.
+    pub fn set_protect_constants(&mut self, protect_constants: bool) {
+        self.protect_constants = protect_constants;
+    }
+
+    /// Give the result of the operations between two currencies in the `currency`,
+    /// `10€ + 5$` is in dollars when the dollar is the default currency.
+    /// None by default, the result is in the currency of the left amount.
+    pub fn set_default_currency(&mut self, currency: Option<Currency>) {
+        self.default_currency = currency;
+    }
+
+    /// Round the money multiplied or divided to the smallest unit of its currency, like the cent or the yen.
+    /// Exact by default, every decimal is kept.
+    pub fn set_money_rounding(&mut self, rounding: MoneyRounding) {
+        self.money_rounding = rounding;
+    }
+
+    /// Round `value` to the smallest unit of its currency with the `money_rounding`, if it's Money
+    fn round_money(&self, value: ResType) -> ResType {
+        let ResType::Money(val, currency) = value else {
+            return value
+        };
+        let factor = 10f64.powi(currency.decimals() as i32);
+        // without the float errors, 1.005 * 100 is 100.49999999999999
+        let cents = ((val * factor) * 1e6).round() / 1e6;
+
+        let cents = match self.money_rounding {
+            MoneyRounding::Exact => return value,
+            MoneyRounding::HalfUp => cents.round(),
+            MoneyRounding::HalfEven => cents.round_ties_even(),
+            MoneyRounding::Floor => cents.floor()
+        };
+        ResType::Money(cents / factor, currency)
+    }
+
+    /// The name under which the variable `name` is stored
+    fn variable_name(&self, name: &str) -> String {
+        if self.case_insensitive {
+            name.to_lowercase()
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// Share the assignments waiting for their variables with other Interpreters,
+    /// like the variables are shared between the lines.
+    pub fn set_definitions(&mut self, definitions: Rc<RefCell<HashMap<String, Definition>>>) {
+        self.definitions = definitions;
+    }
+
+    /// Return `right` converted to the currency of `left` when both are Money, or to its unit when both are quantities.
+    /// 10€ + 5$: the 5$ are converted to euros before the addition.
+    fn same_unit(&self, left: &ResType, right: ResType) -> Result<ResType, Error> {
+        match (left, &right) {
+            (ResType::Money(_, left_currency), ResType::Money(_, right_currency)) if left_currency != right_currency => {
+                self.convert(right, *left_currency)
             },
-            Token::VAR(_) => Ok(self.visit_variable(node)?),
-            Token::ASSIGN => Ok(self.visit_assign(node)?),
-            Token::PLUS | Token::MINUS | Token::MUL | Token::DIV | Token::MONEY(_)=> {
-                match node.children.len() {
-                    1 => Ok(self.visit_unaryop(node)?),
-                    2 => Ok(self.visit_binop(node)?),
-                    _ => panic!("Too many children for an AST node")
-                }             
+            (ResType::Quantity(_, left_unit), ResType::Quantity(_, right_unit)) if left_unit != right_unit => {
+                self.convert_unit(&right, *left_unit)
             },
-            _ => panic!("Unkown Token in the AST")
+            _ => Ok(right)
         }
     }
 
-    fn interpret(&mut self) -> Result<ResType, Error> {
-        let tree = self.parser.parse()?;
-        let result = self.visit(&tree)?;
-        // println!("res: {:?}", result);
-        Ok(result)
+    /// Convert `value` to the `to` currency, using the registered rates
+    fn convert(&self, value: ResType, to: Currency) -> Result<ResType, Error> {
+        convert(value, to, &self.rates)
     }
-}
 
-pub fn solve(input: String, variables: Rc<RefCell<HashMap<String, ResType>>>) -> Result<String, String>{
-    let text = String::from(input.trim());
-    let lexer = Lexer::new(text);
+    /// Convert a quantity to another `unit`, according to the `binary_sizes` mode
+    fn convert_unit(&self, value: &ResType, unit: Unit) -> Result<ResType, Error> {
+        convert_unit(value, unit, self.binary_sizes)
+    }
+
+    /// Convert an angle given by the user to radians, according to the `angle_mode`
+    fn to_radians(&self, angle: f64) -> f64 {
+        match self.angle_mode {
+            AngleMode::Radians => angle,
+            AngleMode::Degrees => angle.to_radians()
+        }
+    }
+
+    /// Convert an angle in radians to the `angle_mode` of the user
+    fn user_angle(&self, angle: f64) -> f64 {
+        match self.angle_mode {
+            AngleMode::Radians => angle,
+            AngleMode::Degrees => angle.to_degrees()
+        }
+    }
+
+    fn visit_num(&self, node: &AST) -> ResType {
+        match node.token {
+            Token::INTEGER(i) => ResType::Int(i),
+            Token::FLOAT(f) => ResType::Float(f),
+            Token::DATE(days) => ResType::Date(days),
+            Token::DURATION(seconds) => ResType::Duration(seconds),
+            Token::QUANTITY(value, unit) => ResType::Quantity(value, unit),
+            Token::IMAGINARY(im) => ResType::complex(0.0, im),
+            _ => panic!("Error: end node is not an integer")
+        }
+    }
+
+    fn visit_variable(&mut self, node: &AST) -> Result<ResType, Error> {
+        match &node.token {
+            Token::VAR(var_name) => {
+                let var_name = &self.variable_name(var_name);
+                let var_list = self.variables.borrow();
+
+                match var_list.get(var_name) {
+                    Some(val) => return Ok(val.clone()),
+                    None => {}
+                };
+
+                // built-in constants, the user can shadow them with an assignment
+                if let Some(val) = constant(var_name) {
+                    return Ok(ResType::Float(val));
+                }
+
+                // if the plural isn't a variable, we check if the singular is one: adultes, boxes
+                if self.plurals {
+                    for suffix in ["s", "es"] {
+                        if let Some(val) = var_name.strip_suffix(suffix).and_then(|singular| var_list.get(singular)) {
+                            return Ok(val.clone());
+                        }
+                    }
+                }
+                drop(var_list);
+
+                // the variable is being computed, and needs its own value through other variables.
+                // `y = y + 1` with an undefined `y` is just an undefined variable.
+                if self.resolving.len() > 1 && self.resolving.contains(var_name) {
+                    return Err(Error::CircularReference);
+                }
+
+                let definition = self.definitions.borrow().get(var_name).cloned();
+                if let Some(Definition(tree)) = definition {
+                    self.resolving.push(var_name.clone());
+                    let result = self.visit(&tree);
+                    self.resolving.pop();
+                    return result;
+                }
+
+                // the imaginary unit, unless the user has a variable `i`
+                if var_name == "i" {
+                    return Ok(ResType::Complex(0.0, 1.0));
+                }
+
+                if let Some(product) = self.split_variables(var_name) {
+                    return self.visit(&product);
+                }
+
+                // probably a typo of a defined variable
+                let suggestion = closest_name(var_name, self.variables.borrow().keys().chain(self.definitions.borrow().keys()));
+                Err(Error::UndefinedVariable(var_name.clone(), suggestion))
+            },
+            _ => panic!("Token is not a variable")
+        }
+    }
+
+    /// The product of the variables written one after the other in `name`, like `a * b` for `ab`.
+    ///
+    /// The longest variable name is taken first from the start of `name`: with `a`, `ab` and `c`
+    /// defined, `abc` is `ab * c`. A variable defined with the whole name is never split.
+    fn split_variables(&self, name: &str) -> Option<AST> {
+        let variables = self.variables.borrow();
+        let starts: Vec<usize> = name.char_indices().map(|(i, _)| i).collect();
+
+        // the end of the longest variable from each start, whose rest can be split too.
+        // From the last start to the first one, so each rest is only split once.
+        let mut next: HashMap<usize, usize> = HashMap::new();
+        for &start in starts.iter().rev() {
+            let longest = starts.iter().copied().chain([name.len()]).rev()
+                .take_while(|&end| end > start)
+                .filter(|&end| start > 0 || end < name.len())
+                .find(|&end| variables.contains_key(&name[start..end]) && (end == name.len() || next.contains_key(&end)));
+            if let Some(end) = longest {
+                next.insert(start, end);
+            }
+        }
+
+        let mut factors = Vec::new();
+        let mut start = 0;
+        while start < name.len() {
+            let end = *next.get(&start)?;
+            factors.push(AST::new(Token::VAR(name[start..end].to_string()), vec![]));
+            start = end;
+        }
+
+        factors.into_iter().rev()
+            .reduce(|rest, prefix| AST::new(Token::MUL, vec![prefix, rest]))
+    }
+
+    fn visit_binop(&mut self, node: &AST) -> Result<ResType, Error> {
+        let left_val = self.visit(&node.children[0])?;
+
+        let right_val = match (&node.token, &node.children[1].token) {
+            // 100 + 10% is 110: the percentage is relative to the left value
+            (Token::PLUS | Token::MINUS, Token::PERCENT) => {
+                let percentage = self.visit(&node.children[1].children[0])?;
+                left_val.clone().checked(percentage, i128::checked_mul, ResType::mul)? / ResType::Int(100)
+            },
+            // 100€ + vat(20%) is 120€: the tax is the rate of the left value.
+            // 120€ - vat(20%) is 96€, 20% less than the price, and not the price before the tax like net(120€, 20%).
+            (Token::PLUS | Token::MINUS, Token::FUNC(name)) if name == "vat" => {
+                let rate = match &node.children[1].children[..] {
+                    [rate] => self.visit(rate)?,
+                    _ => return Err(Error::WrongArgumentCount)
+                };
+                left_val.clone().checked(rate, i128::checked_mul, ResType::mul)?
+            },
+            _ => self.visit(&node.children[1])?
+        };
+
+        self.operation(&node.token, node.pos, left_val, right_val)
+    }
+
+    /// The binary operation `token` on two values, like `Token::PLUS` for `left_val + right_val`.
+    /// The operands that aren't integers are a syntax error at `pos` for the bitwise operators.
+    fn operation(&mut self, token: &Token, pos: usize, left_val: ResType, right_val: ResType) -> Result<ResType, Error> {
+        // both amounts are converted to the default currency
+        let left_val = match (&left_val, &right_val, self.default_currency) {
+            (ResType::Money(_, left), ResType::Money(_, right), Some(currency)) if left != right => self.convert(left_val, currency)?,
+            _ => left_val
+        };
+        let right_val = self.same_unit(&left_val, right_val)?;
+
+        let comparison = matches!(token, Token::LT | Token::GT | Token::LE | Token::GE | Token::EQ | Token::NE);
+        if comparison {
+            return self.comparison(token, &left_val, &right_val);
+        }
+
+        // no arithmetic on booleans
+        if matches!(left_val, ResType::Bool(_)) || matches!(right_val, ResType::Bool(_)) {
+            return Err(Error::OutOfDomain);
+        }
+
+        if matches!(left_val, ResType::Complex(_, _)) || matches!(right_val, ResType::Complex(_, _)) {
+            return complex_arithmetic(token, left_val, right_val);
+        }
+
+        if matches!(left_val, ResType::Date(_)) || matches!(right_val, ResType::Date(_)) {
+            return self.date_arithmetic(token, &left_val, &right_val);
+        }
+
+        if matches!(left_val, ResType::Quantity(_, _)) || matches!(right_val, ResType::Quantity(_, _)) {
+            let res = self.quantity_arithmetic(token, &left_val, &right_val)?;
+            return Ok(scaled_data_size(res, self.binary_sizes));
+        }
+
+        if matches!(left_val, ResType::Duration(_)) || matches!(right_val, ResType::Duration(_)) {
+            return self.duration_arithmetic(token, &left_val, &right_val);
+        }
+
+        match token {
+            Token::PLUS => {
+                left_val.checked(right_val, i128::checked_add, ResType::add)
+            },
+            Token::MINUS => {
+                left_val.checked(right_val, i128::checked_sub, ResType::sub)
+            },
+            Token::MUL => {
+                left_val.checked(right_val, i128::checked_mul, ResType::mul).map(|res| self.round_money(res))
+            },
+            Token::DIV => {
+                // Let's catch division by zero before the happend
+                // because there is no checked_div function for f64.
+                
+                match right_val {
+                    ResType::Int(0) => return Err(Error::DivisonByZero),
+                    ResType::Float(val) => {
+                        if val == 0.0 {return Err(Error::DivisonByZero)}},
+                    _ => {}
+                };
+
+                // 1/3 stays a fraction in the rational mode, unless it overflows
+                if self.rational {
+                    if let Some(res) = left_val.fraction_op(&right_val, fraction_div) {
+                        return Ok(res);
+                    }
+                }
+                // i128::MIN / -1 doesn't fit in an i128
+                if let (ResType::Int(left), ResType::Int(right)) = (&left_val, &right_val) {
+                    if left.checked_div(*right).is_none() {
+                        return Err(Error::Overflow);
+                    }
+                }
+
+                // Division has been implemented as a trait for ResType
+                let res = left_val / right_val;
+                Ok(self.round_money(res))
+            },
+            Token::MOD => {
+                // Same as for the division, a remainder by zero is an error
+                match right_val {
+                    ResType::Int(0) => return Err(Error::DivisonByZero),
+                    ResType::Float(0.0) => return Err(Error::DivisonByZero),
+                    _ => {}
+                };
+
+                left_val.checked(right_val, i128::checked_rem_euclid, ResType::rem)
+            },
+            Token::AND | Token::OR | Token::XOR => {
+                // only on integers, there are no bits to compare on floats or money
+                match (left_val, right_val) {
+                    (ResType::Int(left), ResType::Int(right)) => Ok(ResType::Int(match token {
+                        Token::AND => left & right,
+                        Token::OR => left | right,
+                        _ => left ^ right
+                    })),
+                    _ => Err(Error::InvalidSyntax(pos))
+                }
+            },
+            Token::SHL | Token::SHR => {
+                let (left, right) = match (left_val, right_val) {
+                    (ResType::Int(left), ResType::Int(right)) => (left, right),
+                    _ => return Err(Error::InvalidSyntax(pos))
+                };
+                // a negative shift, or a shift of more than 127 bits
+                let shift = u32::try_from(right).map_err(|_| Error::Overflow)?;
+
+                let res = match token {
+                    Token::SHL => left.checked_shl(shift)
+                        // the bits shifted out must not be lost
+                        .filter(|res| res >> shift == left),
+                    _ => left.checked_shr(shift)
+                };
+                res.map(ResType::Int).ok_or(Error::Overflow)
+            },
+            Token::POW => self.power(left_val, right_val),
+            Token::INTDIV => {
+                if right_val.get_f64() == 0.0 {
+                    return Err(Error::DivisonByZero);
+                }
+
+                // rounded toward negative infinity like the remainder, -7 // 2 is -4
+                match (&left_val, &right_val) {
+                    (ResType::Int(left), ResType::Int(right)) => {
+                        left.checked_div_euclid(*right).map(ResType::Int).ok_or(Error::Overflow)
+                    },
+                    _ => match left_val / right_val {
+                        ResType::Money(val, currency) => Ok(ResType::Money(val.floor(), currency)),
+                        res => {
+                            let val = res.get_f64().floor();
+                            if val.is_finite() && val.abs() < i128::MAX as f64 {
+                                Ok(ResType::Int(val as i128))
+                            } else {
+                                Err(Error::Overflow)
+                            }
+                        }
+                    }
+                }
+            },
+            _ => panic!("Unkown BinOp Token in the AST")
+        }
+    }
+
+    /// `base` to the power `exponent`. The integers stay exact with a positive exponent,
+    /// and with a negative one in the rational mode: 2^-2 is 1/4 instead of 0.25.
+    fn power(&self, base: ResType, exponent: ResType) -> Result<ResType, Error> {
+        if matches!(base, ResType::Money(_, _)) || matches!(exponent, ResType::Money(_, _)) {
+            return Err(Error::OutOfDomain);
+        }
+        if base.get_f64() == 0.0 && exponent.get_f64() < 0.0 {
+            return Err(Error::DivisonByZero);
+        }
+
+        if let ResType::Int(exp) = exponent {
+            let abs_exp = u32::try_from(exp.unsigned_abs()).map_err(|_| Error::Overflow)?;
+
+            match &base {
+                ResType::Int(val) if exp >= 0 => return match val.checked_pow(abs_exp) {
+                    Some(res) => Ok(ResType::Int(res)),
+                    #[cfg(feature = "bigint")]
+                    None => Ok(ResType::big(BigInt::from(*val).pow(abs_exp))),
+                    #[cfg(not(feature = "bigint"))]
+                    None => Err(Error::Overflow)
+                },
+                #[cfg(feature = "bigint")]
+                ResType::BigInt(val) if exp >= 0 => return Ok(ResType::big(val.pow(abs_exp))),
+                _ => {}
+            }
+
+            // the fraction is inverted by a negative exponent, it's a float if it overflows
+            let exact = matches!(base, ResType::Rational(_, _)) || (self.rational && matches!(base, ResType::Int(_)));
+            if let (true, Some((num, den))) = (exact, base.fraction()) {
+                let (num, den) = if exp >= 0 { (num, den) } else { (den, num) };
+                let res = num.checked_pow(abs_exp).zip(den.checked_pow(abs_exp))
+                    .and_then(|(num, den)| ResType::rational(num, den));
+                if let Some(res) = res {
+                    return Ok(res);
+                }
+            }
+        }
+
+        let res = base.get_f64().powf(exponent.get_f64());
+        if res.is_nan() {
+            // like (-8)^(1/3)
+            Err(Error::OutOfDomain)
+        } else if res.is_infinite() {
+            Err(Error::Overflow)
+        } else {
+            Ok(ResType::Float(res))
+        }
+    }
+
+    /// Add days to a date, or count the days between two dates: 2024-03-01 - 2024-02-01 = 29
+    fn date_arithmetic(&self, token: &Token, left_val: &ResType, right_val: &ResType) -> Result<ResType, Error> {
+        let days = |val: i128| i64::try_from(val).map_err(|_| Error::Overflow);
+
+        match (token, left_val, right_val) {
+            (Token::PLUS, ResType::Date(date), ResType::Int(val)) | (Token::PLUS, ResType::Int(val), ResType::Date(date)) => {
+                date.checked_add(days(*val)?).map(ResType::Date).ok_or(Error::Overflow)
+            },
+            (Token::MINUS, ResType::Date(date), ResType::Int(val)) => {
+                date.checked_sub(days(*val)?).map(ResType::Date).ok_or(Error::Overflow)
+            },
+            (Token::MINUS, ResType::Date(left), ResType::Date(right)) => Ok(ResType::Int((left - right) as i128)),
+            _ => Err(Error::OutOfDomain)
+        }
+    }
+
+    /// Add up the durations, or scale them with a number: 1h + 30min, 2h * 3, 1h / 2
+    fn duration_arithmetic(&self, token: &Token, left_val: &ResType, right_val: &ResType) -> Result<ResType, Error> {
+        // a number, but not a date, some money or a boolean
+        let is_number = |val: &ResType| matches!(val, ResType::Int(_) | ResType::Float(_) | ResType::Rational(_, _));
+
+        let (left, right) = (left_val.get_f64(), right_val.get_f64());
+        let both = matches!((left_val, right_val), (ResType::Duration(_), ResType::Duration(_)));
+        let divisor_is_zero = matches!(token, Token::DIV | Token::MOD) && right == 0.0;
+        if divisor_is_zero {
+            return Err(Error::DivisonByZero);
+        }
+
+        match token {
+            Token::PLUS if both => Ok(ResType::Duration(left + right)),
+            Token::MINUS if both => Ok(ResType::Duration(left - right)),
+            Token::MOD if both => Ok(ResType::Duration(left.rem_euclid(right))),
+            // 2h / 30min is 4
+            Token::DIV if both => Ok(ResType::Float(left / right)),
+            Token::MUL if is_number(left_val) || is_number(right_val) => Ok(ResType::Duration(left * right)),
+            Token::DIV if is_number(right_val) => Ok(ResType::Duration(left / right)),
+            _ => Err(Error::OutOfDomain)
+        }
+    }
+
+    /// Add up the quantities, which have been converted to the same unit, or scale them with a number:
+    /// 1km + 500m is 1.5 km. The units must measure the same thing, 1m + 1h is an error.
+    ///
+    /// The temperatures can only be converted and compared. 20C + 10C isn't 30C,
+    /// as 20C is 68F and 10C is 50F, but 68F + 50F is 118F or 47.8C.
+    fn quantity_arithmetic(&self, token: &Token, left_val: &ResType, right_val: &ResType) -> Result<ResType, Error> {
+        let is_number = |val: &ResType| matches!(val, ResType::Int(_) | ResType::Float(_) | ResType::Rational(_, _));
+        let is_temperature = |val: &ResType| matches!(val, ResType::Quantity(_, unit) if unit.dimension() == Dimension::Temperature);
+
+        if is_temperature(left_val) || is_temperature(right_val) {
+            return Err(Error::OutOfDomain);
+        }
+
+        match (left_val, right_val) {
+            (ResType::Quantity(left, unit), ResType::Quantity(right, _)) => {
+                match token {
+                    Token::PLUS => Ok(ResType::Quantity(left + right, *unit)),
+                    Token::MINUS => Ok(ResType::Quantity(left - right, *unit)),
+                    // 1km / 500m is 2
+                    Token::DIV if *right == 0.0 => Err(Error::DivisonByZero),
+                    Token::DIV => Ok(ResType::Float(left / right)),
+                    _ => Err(Error::IncompatibleUnits)
+                }
+            },
+            (ResType::Quantity(left, unit), right) if is_number(right) => match token {
+                Token::MUL => Ok(ResType::Quantity(left * right.get_f64(), *unit)),
+                Token::DIV if right.get_f64() == 0.0 => Err(Error::DivisonByZero),
+                Token::DIV => Ok(ResType::Quantity(left / right.get_f64(), *unit)),
+                _ => Err(Error::IncompatibleUnits)
+            },
+            (left, ResType::Quantity(right, unit)) if is_number(left) && *token == Token::MUL => {
+                Ok(ResType::Quantity(left.get_f64() * right, *unit))
+            },
+            _ => Err(Error::IncompatibleUnits)
+        }
+    }
+
+    /// Compare two numbers, the money has been converted to the same currency.
+    /// Booleans can only be compared with `==` and `!=`.
+    fn comparison(&self, token: &Token, left_val: &ResType, right_val: &ResType) -> Result<ResType, Error> {
+        let ordering = match (left_val, right_val) {
+            (ResType::Bool(left), ResType::Bool(right)) if matches!(token, Token::EQ | Token::NE) => left.cmp(right),
+            (ResType::Bool(_), _) | (_, ResType::Bool(_)) => return Err(Error::OutOfDomain),
+            (ResType::Date(left), ResType::Date(right)) => left.cmp(right),
+            (ResType::Date(_), _) | (_, ResType::Date(_)) => return Err(Error::OutOfDomain),
+            (ResType::Duration(left), ResType::Duration(right)) => match left.partial_cmp(right) {
+                Some(ordering) => ordering,
+                None => return Ok(ResType::Bool(*token == Token::NE))
+            },
+            (ResType::Duration(_), _) | (_, ResType::Duration(_)) => return Err(Error::OutOfDomain),
+            (ResType::Quantity(_, _), ResType::Quantity(_, _)) => match left_val.compare(right_val) {
+                Some(ordering) => ordering,
+                None => return Ok(ResType::Bool(*token == Token::NE))
+            },
+            (ResType::Quantity(_, _), _) | (_, ResType::Quantity(_, _)) => return Err(Error::IncompatibleUnits),
+            // the complex numbers are only equal or not
+            (ResType::Complex(_, _), _) | (_, ResType::Complex(_, _)) if matches!(token, Token::EQ | Token::NE) => {
+                let equal = left_val.complex_parts() == right_val.complex_parts();
+                return Ok(ResType::Bool(equal == (*token == Token::EQ)))
+            },
+            (ResType::Complex(_, _), _) | (_, ResType::Complex(_, _)) => return Err(Error::OutOfDomain),
+            // NaN isn't equal to anything
+            _ => match left_val.compare(right_val) {
+                Some(ordering) => ordering,
+                None => return Ok(ResType::Bool(*token == Token::NE))
+            }
+        };
+
+        Ok(ResType::Bool(match token {
+            Token::LT => ordering == Ordering::Less,
+            Token::GT => ordering == Ordering::Greater,
+            Token::LE => ordering != Ordering::Greater,
+            Token::GE => ordering != Ordering::Less,
+            Token::EQ => ordering == Ordering::Equal,
+            _ => ordering != Ordering::Equal
+        }))
+    }
+
+    fn visit_unaryop(&mut self, node: &AST) -> Result<ResType, Error> {
+        let val = self.visit(&node.children[0])?;
+        if matches!(val, ResType::Bool(_) | ResType::Date(_)) {
+            return Err(Error::OutOfDomain);
+        }
+
+        match &node.token {
+            Token::PLUS  => {  Ok(val) },
+            Token::MINUS => {
+                match val {
+                    ResType::Int(val) => val.checked_neg().map(ResType::Int).ok_or(Error::Overflow),
+                    ResType::Rational(num, den) => num.checked_neg().map(|num| ResType::Rational(num, den)).ok_or(Error::Overflow),
+                    // -1K is colder than the absolute zero
+                    _ => above_absolute_zero(-val)
+                }
+            },
+            Token::MONEY(currency) => {
+                let number = self.visit(&node.children[0])?;
+
+                match number {
+                    ResType::Money(_, _) | ResType::Bool(_) | ResType::Date(_) | ResType::Duration(_) | ResType::Quantity(_, _)
+                        | ResType::Complex(_, _) => {
+                        Err(Error::OutOfDomain)
+                    },
+                    _ => Ok(ResType::Money(number.get_f64(), *currency))
+                }
+
+            }
+            _ => {panic!("Invalid token type for an unary node")}
+        }
+    }
+
+    fn visit_func(&mut self, node: &AST) -> Result<ResType, Error> {
+        let arguments = node.children.iter()
+            .map(|child| self.visit(child))
+            .collect::<Result<Vec<ResType>, Error>>()?;
+
+        // the other functions are only defined on the real numbers
+        let complex_function = matches!(&node.token, Token::FUNC(name) if ["abs", "conj", "pow"].contains(&name.as_str()));
+        if !complex_function && arguments.iter().any(|argument| matches!(argument, ResType::Complex(_, _))) {
+            return Err(Error::OutOfDomain);
+        }
+
+        // Most functions take a single argument
+        let single_argument = || match &arguments[..] {
+            [argument] => Ok(argument.clone()),
+            _ => Err(Error::WrongArgumentCount)
+        };
+
+        match &node.token {
+            Token::FUNC(name) => {
+                match name.as_str() {
+                    "sqrt" => {
+                        let argument = single_argument()?;
+                        let val = argument.get_f64();
+                        if val < 0.0 {
+                            return Err(Error::OutOfDomain);
+                        }
+
+                        let res = val.sqrt();
+
+                        // sqrt(16) = 4, but sqrt(2) = 1.4142135623730951
+                        if matches!(argument, ResType::Int(_)) && res.fract() == 0.0 {
+                            Ok(ResType::Int(res as i128))
+                        } else {
+                            Ok(ResType::Float(res))
+                        }
+                    },
+                    "abs" => match single_argument()? {
+                        ResType::Int(val) => val.checked_abs().map(ResType::Int).ok_or(Error::Overflow),
+                        ResType::Float(val) => Ok(ResType::Float(val.abs())),
+                        ResType::Money(val, currency) => Ok(ResType::Money(val.abs(), currency)),
+                        ResType::Rational(num, den) => num.checked_abs().map(|num| ResType::Rational(num, den)).ok_or(Error::Overflow),
+                        ResType::Duration(seconds) => Ok(ResType::Duration(seconds.abs())),
+                        ResType::Quantity(val, unit) => Ok(ResType::Quantity(val.abs(), unit)),
+                        // the magnitude: abs(3+4i) = 5
+                        ResType::Complex(re, im) => Ok(ResType::complex(re.hypot(im), 0.0)),
+                        ResType::Bool(_) | ResType::Date(_) | ResType::Variables(_) => Err(Error::OutOfDomain),
+                        #[cfg(feature = "bigint")]
+                        ResType::BigInt(val) => Ok(ResType::BigInt(val.abs()))
+                    },
+                    // round(2.5) is 3, the halves are rounded away from zero
+                    "round" => Ok(rounded(single_argument()?, f64::round)),
+                    "floor" => Ok(rounded(single_argument()?, f64::floor)),
+                    "ceil" => Ok(rounded(single_argument()?, f64::ceil)),
+                    // trunc(-2.9) is -2, the decimals are dropped
+                    "trunc" => Ok(rounded(single_argument()?, f64::trunc)),
+                    // sign(-3) is -1, sign(0) is 0 and sign(12€) is 1
+                    "sign" => match single_argument()? {
+                        ResType::Bool(_) | ResType::Date(_) => Err(Error::OutOfDomain),
+                        argument => {
+                            let val = argument.get_f64();
+                            Ok(ResType::Int(i128::from(val > 0.0) - i128::from(val < 0.0)))
+                        }
+                    },
+
+                    // min(3, 7, 1), the money is converted to compare it
+                    "min" | "max" => {
+                        if arguments.len() < 2 {
+                            return Err(Error::WrongArgumentCount);
+                        }
+                        let wanted = if name == "min" { Ordering::Less } else { Ordering::Greater };
+
+                        let mut extreme = &arguments[0];
+                        for argument in &arguments[1..] {
+                            let converted = self.same_unit(extreme, argument.clone())?;
+                            match converted.compare(extreme) {
+                                Some(ordering) if ordering == wanted => extreme = argument,
+                                Some(_) => {},
+                                None => return Err(Error::OutOfDomain)
+                            }
+                        }
+                        Ok(extreme.clone())
+                    },
+
+                    // clamp(15, 0, 10) is 10, the value restricted to the range between lo and hi
+                    "clamp" => {
+                        let [value, lo, hi] = &arguments[..] else {
+                            return Err(Error::WrongArgumentCount);
+                        };
+                        let (lo_converted, hi_converted) = (self.same_unit(value, lo.clone())?, self.same_unit(value, hi.clone())?);
+
+                        match (hi_converted.compare(&lo_converted), value.compare(&lo_converted), value.compare(&hi_converted)) {
+                            (None, _, _) | (_, None, _) | (_, _, None) | (Some(Ordering::Less), _, _) => Err(Error::OutOfDomain),
+                            (_, Some(Ordering::Less), _) => Ok(lo.clone()),
+                            (_, _, Some(Ordering::Greater)) => Ok(hi.clone()),
+                            _ => Ok(value.clone())
+                        }
+                    },
+
+                    // nCr(5, 2) = 10 ways to choose 2 items out of 5, and nPr(5, 2) = 20 if their order matters.
+                    // The arguments that aren't two integers 0 <= k <= n are a syntax error at the name of the function.
+                    "nCr" | "nPr" => {
+                        let (n, k) = match &arguments[..] {
+                            [ResType::Int(n), ResType::Int(k)] if *k >= 0 && k <= n => (*n, *k),
+                            [_, _] => return Err(Error::InvalidSyntax(node.pos)),
+                            _ => return Err(Error::WrongArgumentCount)
+                        };
+                        let res = if name == "nCr" { combinations(n, k) } else { permutations(n, k) };
+                        res.map(ResType::Int).ok_or(Error::Overflow)
+                    },
+
+                    // gcd(12, 18) = 6 and lcm(4, 6) = 12, with two integers or more
+                    "gcd" | "lcm" => {
+                        if arguments.len() < 2 {
+                            return Err(Error::WrongArgumentCount);
+                        }
+                        let op = if name == "gcd" { gcd } else { lcm };
+
+                        let integers = arguments.iter()
+                            .map(|argument| match argument {
+                                ResType::Int(val) => Ok(*val),
+                                _ => Err(Error::OutOfDomain)
+                            })
+                            .collect::<Result<Vec<i128>, Error>>()?;
+                        integers[1..].iter()
+                            .try_fold(integers[0], |res, &val| op(res, val))
+                            .map(ResType::Int)
+                            .ok_or(Error::Overflow)
+                    },
+
+                    // the money is converted to the currency of the first argument
+                    "sum" | "avg" if arguments.is_empty() => Err(Error::WrongArgumentCount),
+                    "sum" => total(&arguments, &self.rates),
+                    "avg" => {
+                        let sum = total(&arguments, &self.rates)?;
+                        Ok(sum / ResType::Int(arguments.len() as i128))
+                    },
+
+                    // Trigonometry is done with floats, so sin(pi) is
+                    // a tiny number like 1.2246467991473532e-16, not exactly 0.
+                    "sin" => Ok(ResType::Float(self.to_radians(single_argument()?.get_f64()).sin())),
+                    "cos" => Ok(ResType::Float(self.to_radians(single_argument()?.get_f64()).cos())),
+                    "tan" => Ok(ResType::Float(self.to_radians(single_argument()?.get_f64()).tan())),
+                    // deg(pi) = 180, whatever the angle mode
+                    "deg" => Ok(ResType::Float(single_argument()?.get_f64().to_degrees())),
+                    "rad" => Ok(ResType::Float(single_argument()?.get_f64().to_radians())),
+                    // the angle of the point (x, y), atan2(y, x) like in most languages
+                    "atan2" => match &arguments[..] {
+                        [y, x] => Ok(ResType::Float(self.user_angle(y.get_f64().atan2(x.get_f64())))),
+                        _ => Err(Error::WrongArgumentCount)
+                    },
+                    // hypot(3, 4) = 5, the length of the hypotenuse
+                    // the conjugate: conj(3+4i) = 3-4i, a real number is its own conjugate
+                    "conj" => match single_argument()? {
+                        ResType::Complex(re, im) => Ok(ResType::Complex(re, -im)),
+                        argument => Ok(argument)
+                    },
+                    "hypot" => match &arguments[..] {
+                        [x, y] => Ok(ResType::Float(x.get_f64().hypot(y.get_f64()))),
+                        _ => Err(Error::WrongArgumentCount)
+                    },
+
+                    // the cube root of a negative number is negative: cbrt(-8) = -2
+                    "cbrt" => {
+                        let argument = single_argument()?;
+                        Ok(integer_root(&argument, argument.get_f64().cbrt(), 3.0))
+                    },
+                    // root(16, 4) = 2, the odd roots of the negative numbers are negative like cbrt
+                    "root" => {
+                        let (argument, n) = match &arguments[..] {
+                            [argument, n] => (argument, n.get_f64()),
+                            _ => return Err(Error::WrongArgumentCount)
+                        };
+                        let val = argument.get_f64();
+                        let odd = n.fract() == 0.0 && n % 2.0 != 0.0;
+                        if n == 0.0 || (val < 0.0 && !odd) {
+                            return Err(Error::OutOfDomain);
+                        }
+
+                        let res = if val < 0.0 { -(-val).powf(1.0 / n) } else { val.powf(1.0 / n) };
+                        Ok(integer_root(argument, res, n))
+                    },
+                    // the tax only has a meaning after a price, like `100€ + vat(20%)`:
+                    // alone, or before the price, it would silently be a rate of 0.2
+                    "vat" => Err(Error::OutOfDomain),
+                    // net(120€, 20%) is 100€, the price before the tax
+                    "net" => match &arguments[..] {
+                        [price, rate] => {
+                            let divisor = ResType::Int(1).checked(rate.clone(), i128::checked_add, ResType::add)?;
+                            if divisor.get_f64() == 0.0 {
+                                return Err(Error::DivisonByZero);
+                            }
+                            Ok(price.clone() / divisor)
+                        },
+                        _ => Err(Error::WrongArgumentCount)
+                    },
+                    // change(100, 120) is 20, the growth in percent from the old value to the new one
+                    "change" => match &arguments[..] {
+                        [old, new] => {
+                            let new = self.same_unit(old, new.clone())?;
+                            let old = old.get_f64();
+                            if old == 0.0 {
+                                return Err(Error::DivisonByZero);
+                            }
+                            Ok(ResType::Float((new.get_f64() - old) / old * 100.0))
+                        },
+                        _ => Err(Error::WrongArgumentCount)
+                    },
+                    // a float between 0 included and 1 excluded
+                    "random" => match &arguments[..] {
+                        [] => Ok(ResType::Float(rand::thread_rng().gen())),
+                        _ => Err(Error::WrongArgumentCount)
+                    },
+                    // randint(1, 6) rolls a dice, both bounds can be drawn
+                    "randint" => match &arguments[..] {
+                        [ResType::Int(low), ResType::Int(high)] if low <= high => Ok(ResType::Int(rand::thread_rng().gen_range(*low..=*high))),
+                        [_, _] => Err(Error::OutOfDomain),
+                        _ => Err(Error::WrongArgumentCount)
+                    },
+                    "exp" => {
+                        let res = single_argument()?.get_f64().exp();
+                        if res.is_infinite() {
+                            return Err(Error::Overflow);
+                        }
+                        Ok(ResType::Float(res))
+                    },
+                    // pow(2, 10) is 2^10
+                    "pow" => match &arguments[..] {
+                        // like `^`, with the same types
+                        [base, exponent] => self.operation(&Token::POW, node.pos, base.clone(), exponent.clone()),
+                        _ => Err(Error::WrongArgumentCount)
+                    },
+
+                    // Logarithms are only defined for positive numbers
+                    "ln" | "log2" => {
+                        let val = single_argument()?.get_f64();
+                        if val <= 0.0 {
+                            return Err(Error::OutOfDomain);
+                        }
+
+                        match name.as_str() {
+                            "ln" => Ok(ResType::Float(val.ln())),
+                            _ => Ok(ResType::Float(val.log2()))
+                        }
+                    },
+                    // log(x) is in base 10, log(x, base) in any base
+                    "log" => {
+                        let (val, base) = match &arguments[..] {
+                            [val] => (val.get_f64(), 10.0),
+                            [val, base] => (val.get_f64(), base.get_f64()),
+                            _ => return Err(Error::WrongArgumentCount)
+                        };
+
+                        if val <= 0.0 || base <= 0.0 || base == 1.0 {
+                            return Err(Error::OutOfDomain);
+                        }
+
+                        if base == 10.0 {
+                            Ok(ResType::Float(val.log10()))
+                        } else {
+                            Ok(ResType::Float(val.log(base)))
+                        }
+                    },
+                    _ => Err(Error::UnknownFunction)
+                }
+            },
+            _ => panic!("Token is not a function")
+        }
+    }
+
+    fn visit_factorial(&mut self, node: &AST) -> Result<ResType, Error> {
+        let val = match self.visit(&node.children[0])? {
+            ResType::Int(val) if val >= 0 => val,
+            _ => return Err(Error::OutOfDomain)
+        };
+
+        // even the big integers would take too long
+        if val > MAX_FACTORIAL {
+            return Err(Error::Overflow);
+        }
+
+        let mut res = ResType::Int(1);
+        for i in 2..=val {
+            res = res.checked(ResType::Int(i), i128::checked_mul, ResType::mul)?;
+        }
+        Ok(res)
+    }
+
+    /// 50% is 0.5
+    fn visit_percent(&mut self, node: &AST) -> Result<ResType, Error> {
+        let val = self.visit(&node.children[0])?;
+        Ok(ResType::Float(val.get_f64() / 100.0))
+    }
+
+    /// X% of Y is X * Y / 100, the currency of Y is kept: 10% of 250€ = 25€
+    fn visit_percent_of(&mut self, node: &AST) -> Result<ResType, Error> {
+        let percentage = self.visit(&node.children[0].children[0])?;
+        let val = self.visit(&node.children[1])?;
+
+        Ok(percentage.checked(val, i128::checked_mul, ResType::mul)? / ResType::Int(100))
+    }
+
+    /// cond ? a : b, only the branch that is taken is computed
+    fn visit_ternary(&mut self, node: &AST) -> Result<ResType, Error> {
+        match self.visit(&node.children[0])? {
+            ResType::Bool(true) => self.visit(&node.children[1]),
+            ResType::Bool(false) => self.visit(&node.children[2]),
+            _ => Err(Error::OutOfDomain)
+        }
+    }
+
+    /// 10€ in $, a value without a currency just gets one: 5 in $ = 5$
+    fn visit_conversion(&mut self, node: &AST) -> Result<ResType, Error> {
+        let val = self.visit(&node.children[0])?;
+
+        match node.children[1].token {
+            Token::MONEY(currency) => self.convert(val, currency),
+            Token::UNIT(unit) => self.convert_unit(&val, unit),
+            _ => panic!("Conversion to something that is not a currency or a unit")
+        }
+    }
+
+    fn visit_assign(&mut self, node: &AST) -> Result<ResType, Error> {
+        let var_name = match &node.children[0].token {
+            Token::VAR(var_name) => self.variable_name(var_name),
+            _ => panic!("Assignement without a variable")
+        };
+        if self.protect_constants && constant(&var_name).is_some() {
+            return Err(Error::ProtectedConstant);
+        }
+
+        self.resolving.push(var_name.clone());
+        let right_val = self.visit(&node.children[1]);
+        self.resolving.pop();
+
+        match right_val {
+            Ok(right_val) => {
+                self.definitions.borrow_mut().remove(&var_name);
+                self.variables.borrow_mut().insert(var_name, right_val.clone());
+                Ok(right_val)
+            },
+            Err(Error::UndefinedVariable(name, suggestion)) => {
+                // computed later, when the missing variables are defined
+                self.definitions.borrow_mut().insert(var_name, Definition(node.children[1].clone()));
+                Err(Error::UndefinedVariable(name, suggestion))
+            },
+            Err(error) => Err(error)
+        }
+    }
+
+    /// Remove the variable, and give its last value.
+    /// Deleting a variable without a value is an UndefinedVariable error, like using it.
+    fn visit_delete(&mut self, node: &AST) -> Result<ResType, Error> {
+        let var_name = match &node.children[0].token {
+            Token::VAR(var_name) => self.variable_name(var_name),
+            _ => panic!("Deletion without a variable")
+        };
+
+        // the pending assignment won't be computed
+        self.definitions.borrow_mut().remove(&var_name);
+        self.variables.borrow_mut().remove(&var_name).ok_or(Error::UndefinedVariable(var_name, None))
+    }
+
+    /// Run the statements one after the other, the assignments are seen by the next ones
+    fn visit_program(&mut self, node: &AST) -> Result<ResType, Error> {
+        let mut result = ResType::Int(0);
+        for statement in &node.children {
+            result = self.visit(statement)?;
+        }
+        Ok(result)
+    }
+
+    fn visit(&mut self, node: &AST) -> Result<ResType, Error> {
+        match node.token {
+            Token::INTEGER(_) | Token::FLOAT(_) | Token::DATE(_) | Token::DURATION(_) | Token::QUANTITY(_, _)
+                | Token::IMAGINARY(_) => {
+                Ok(self.visit_num(node))
+            },
+            Token::VAR(_) => Ok(self.visit_variable(node)?),
+            Token::ASSIGN => Ok(self.visit_assign(node)?),
+            Token::DEL => Ok(self.visit_delete(node)?),
+            Token::SEMICOLON => Ok(self.visit_program(node)?),
+            // floor(-459.6F) would be colder than the absolute zero
+            Token::FUNC(_) => above_absolute_zero(self.visit_func(node)?),
+            Token::FACT => Ok(self.visit_factorial(node)?),
+            Token::PERCENT => Ok(self.visit_percent(node)?),
+            Token::OF => Ok(self.visit_percent_of(node)?),
+            Token::IN => Ok(self.visit_conversion(node)?),
+            Token::QUESTION => Ok(self.visit_ternary(node)?),
+            Token::PLUS | Token::MINUS | Token::MUL | Token::DIV | Token::INTDIV | Token::MOD | Token::POW | Token::MONEY(_)
+                | Token::AND | Token::OR | Token::XOR | Token::SHL | Token::SHR
+                | Token::LT | Token::GT | Token::LE | Token::GE | Token::EQ | Token::NE => {
+                match node.children.len() {
+                    1 => Ok(self.visit_unaryop(node)?),
+                    2 => Ok(self.visit_binop(node)?),
+                    _ => panic!("Too many children for an AST node")
+                }             
+            },
+            _ => panic!("Unkown Token in the AST")
+        }
+    }
+
+    pub fn interpret(&mut self) -> Result<ResType, Error> {
+        Ok(self.interpret_statement()?.value)
+    }
+
+    /// Like `interpret`, but also tell if the statement was an assignment
+    pub fn interpret_statement(&mut self) -> Result<Solution, Error> {
+        let tree = self.parser.parse()?;
+        let value = self.visit(&tree)?;
+
+        // the value is the one of the last statement
+        let last = match tree.token {
+            Token::SEMICOLON => tree.children.last().unwrap(),
+            _ => &tree
+        };
+        let kind = match (&last.token, last.children.first().map(|child| &child.token)) {
+            (Token::ASSIGN, Some(Token::VAR(name))) => StatementKind::Assignment { name: name.clone() },
+            _ => StatementKind::Expression
+        };
+        Ok(Solution { kind, value })
+    }
+}
+
+/// What kind of statement a line is
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum StatementKind {
+    Expression,
+    /// The value is assigned to the variable `name`
+    Assignment { name: String }
+}
+
+/// The result of a statement, and what it is
+#[derive(Debug, PartialEq, Clone)]
+pub struct Solution {
+    pub kind: StatementKind,
+    pub value: ResType
+}
+
+pub fn solve(input: String, variables: Rc<RefCell<HashMap<String, ResType>>>, rates: &HashMap<(Currency, Currency), f64>) -> Result<ResType, String>{
+    solve_statement(input, variables, rates).map(|solution| solution.value)
+}
+
+/// Like `solve`, with the Error instead of its message.
+/// The position of an InvalidSyntax error is in the `input`, with its indentation.
+pub fn solve_typed(input: String, variables: Rc<RefCell<HashMap<String, ResType>>>, rates: &HashMap<(Currency, Currency), f64>) -> Result<ResType, Error> {
+    solve_line(input, variables, Rc::default(), rates, AngleMode::Radians).map(|solution| solution.value)
+}
+
+/// Like `solve`, but also tell if the line was an assignment
+pub fn solve_statement(input: String, variables: Rc<RefCell<HashMap<String, ResType>>>, rates: &HashMap<(Currency, Currency), f64>) -> Result<Solution, String>{
+    solve_line(input, variables, Rc::default(), rates, AngleMode::Radians).map_err(|error| error.to_string())
+}
+
+/// Like `solve_statement`, with the Error instead of its message, and the assignments waiting for their variables
+fn solve_line(input: String, variables: Rc<RefCell<HashMap<String, ResType>>>, definitions: Rc<RefCell<HashMap<String, Definition>>>, rates: &HashMap<(Currency, Currency), f64>, angle_mode: AngleMode) -> Result<Solution, Error> {
+    let text = String::from(input.trim());
+    let mut lexer = Lexer::new(text);
+    lexer.set_variables(variables.borrow().keys().cloned().collect());
+
+    let result = Parser::new(lexer).and_then(|parser| {
+        let mut interpreter = Interpreter::new(parser, variables);
+        interpreter.set_definitions(definitions);
+        interpreter.set_angle_mode(angle_mode);
+        for (&(from, to), &rate) in rates {
+            interpreter.add_rate(from, to, rate);
+        }
+
+        interpreter.interpret_statement()
+    });
+
+    // the position is in the trimmed input, not in the line given by the user
+    result.map_err(|error| match error {
+        Error::InvalidSyntax(pos) => Error::InvalidSyntax(input.chars().take_while(|c| c.is_whitespace()).count() + pos),
+        error => error
+    })
+}
+
+/// Solve each line of `text` like the lines of the Luca window. The following lines can use
+/// the variables assigned on a line, the previous result `ans`, the result of a line with
+/// `line1`, `line2`... and the sum of the previous results `total`.
+///
+/// The blank lines and the `#` comments are None. An error doesn't stop the next lines.
+///
+/// A line ending with an operator, or with a parenthesis left open, continues on the next lines:
+/// the statement is solved on its last line, and the lines before it are None like the blank lines.
+pub fn solve_lines(text: &str, variables: Rc<RefCell<HashMap<String, ResType>>>, rates: &HashMap<(Currency, Currency), f64>) -> Vec<Option<Result<Solution, String>>> {
+    let mut state = LineState { variables, ..Default::default() };
+
+    text.lines().enumerate()
+        .map(|(number, line)| state.solve(number, line, rates))
+        .collect()
+}
+
+/// Solve a whole document with `solve_lines`, without any variable or conversion rate to begin with.
+///
+/// There's one result per line, the blank lines, the comments and the lines continued on the next one
+/// are an InvalidSyntax error like an empty line given to `solve`.
+/// A statement still unfinished at the end of the text, like `2 +`, gives its error on its last line.
+pub fn solve_document(text: &str) -> Vec<Result<ResType, Error>> {
+    let mut state = LineState::default();
+    let rates = HashMap::new();
+
+    let mut results: Vec<Result<ResType, Error>> = text.lines().enumerate()
+        .map(|(number, line)| state.solve_typed(number, line, &rates))
+        .map(|line| line.unwrap_or(Err(Error::InvalidSyntax(0))).map(|solution| solution.value))
+        .collect();
+
+    if let Some((number, result)) = state.finish(&rates) {
+        results[number] = result.map(|solution| solution.value);
+    }
+    results
+}
+
+/// The `statement` goes on with the next line: it ends with an operator, like `2 +`,
+/// or a parenthesis is still open. The `#` comment isn't part of the statement.
+fn continues(statement: &str) -> bool {
+    let code = without_comment(statement);
+    let opened = code.matches('(').count() > code.matches(')').count();
+    opened || code.trim_end().ends_with(['+', '-', '*', '/', '^', '&', '|', '<', '>', '=', ',', '?', ':'])
+}
+
+/// Tell if the `statement` reads the variable `name`, and if it assigns it:
+/// `x = total * 2` reads `total`, `total = 5` assigns it
+fn uses_variable(statement: &str, name: &str) -> (bool, bool) {
+    let mut lexer = Lexer::new(statement.to_string());
+    let (mut reads, mut assigns) = (false, false);
+    let mut previous = None;
+
+    loop {
+        let token = match lexer.get_next_token() {
+            Ok(Token::EOF) | Err(_) => Token::EOF,
+            Ok(token) => token
+        };
+
+        if matches!(&previous, Some(Token::VAR(var)) if var == name) {
+            match token {
+                Token::ASSIGN => assigns = true,
+                // `total += 5` needs the previous value
+                Token::PLUSASSIGN | Token::MINUSASSIGN | Token::MULASSIGN | Token::DIVASSIGN => {
+                    reads = true;
+                    assigns = true;
+                },
+                _ => reads = true
+            }
+        }
+
+        if token == Token::EOF {
+            return (reads, assigns);
+        }
+        previous = Some(token);
+    }
+}
+
+/// The `line` before its `#` comment
+fn without_comment(line: &str) -> &str {
+    line.split('#').next().unwrap_or(line)
+}
+
+/// The variables given by `LineState` to each line, like `ans`, `line2` or `total`
+pub fn is_line_variable(name: &str) -> bool {
+    let line_reference = name.strip_prefix("line")
+        .is_some_and(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()));
+
+    name == "ans" || name == "total" || line_reference
+}
+
+/// The variables assigned by the user, sorted by name, for the keyword `vars`.
+/// The variables of `LineState` like `ans` aren't listed.
+pub fn variables_listing(variables: &HashMap<String, ResType>) -> ResType {
+    let mut listing: Vec<(String, ResType)> = variables.iter()
+        .filter(|(name, _)| !is_line_variable(name))
+        .map(|(name, value)| (name.clone(), value.clone()))
+        .collect();
+    listing.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    ResType::Variables(listing)
+}
+
+/// What the previous lines of a document give to the next one with `solve_lines`:
+/// the variables and the sum `total`.
+///
+/// A copy of the state can solve the rest of a document again, without the lines before it.
+#[derive(Debug)]
+pub struct LineState {
+    variables: Rc<RefCell<HashMap<String, ResType>>>,
+    /// The assignments of the previous lines waiting for their variables, like `a = b + 1` before `b = 4`
+    definitions: Rc<RefCell<HashMap<String, Definition>>>,
+    /// The error of the sum, like NoConversionRate, when the results can't be summed
+    total: Result<ResType, Error>,
+    /// The unit of the angles of the trigonometric functions, for all the lines
+    angle_mode: AngleMode,
+    /// The start of a statement continued on the next line, and the number of its last line
+    pending: Option<(usize, String)>,
+    /// The last statement solved, with the lines it was continued on
+    statement: String,
+    /// The user assigned a variable `total`, it replaces the sum of the lines
+    user_total: bool
+}
+
+impl Default for LineState {
+    /// The state of the first line
+    fn default() -> Self {
+        LineState {
+            variables: Rc::new(RefCell::new(HashMap::new())),
+            definitions: Rc::new(RefCell::new(HashMap::new())),
+            total: Ok(ResType::Int(0)),
+            angle_mode: AngleMode::Radians,
+            pending: None,
+            statement: String::new(),
+            user_total: false
+        }
+    }
+}
+
+impl Clone for LineState {
+    /// The variables and the definitions are copied, they aren't shared with the clone
+    fn clone(&self) -> Self {
+        LineState {
+            variables: Rc::new(RefCell::new(self.variables.borrow().clone())),
+            definitions: Rc::new(RefCell::new(self.definitions.borrow().clone())),
+            total: self.total.clone(),
+            angle_mode: self.angle_mode,
+            pending: self.pending.clone(),
+            statement: self.statement.clone(),
+            user_total: self.user_total
+        }
+    }
+}
+
+impl LineState {
+    /// The variables available to the next line
+    pub fn variables(&self) -> std::cell::Ref<'_, HashMap<String, ResType>> {
+        self.variables.borrow()
+    }
+
+    /// The text of the last statement solved, like `price = 12 + 8` for the lines `price = 12 +` and `8`
+    pub fn statement(&self) -> &str {
+        &self.statement
+    }
+
+    /// Solve the next lines with the angles in radians or in degrees. In radians by default.
+    pub fn set_angle_mode(&mut self, angle_mode: AngleMode) {
+        self.angle_mode = angle_mode;
+    }
+
+    /// Solve the `line` with the index `number` in the document, like `solve_lines`,
+    /// and update the state for the next line.
+    pub fn solve(&mut self, number: usize, line: &str, rates: &HashMap<(Currency, Currency), f64>) -> Option<Result<Solution, String>> {
+        self.solve_with(number, line, rates, |error| error.to_string())
+    }
+
+    /// Like `solve`, with the Error instead of its message
+    pub fn solve_typed(&mut self, number: usize, line: &str, rates: &HashMap<(Currency, Currency), f64>) -> Option<Result<Solution, Error>> {
+        self.solve_with(number, line, rates, |error| error)
+    }
+
+    /// Solve the statement still waiting for its next line at the end of a document, like `2 +`,
+    /// and give the number of its last line. None if the last statement is complete.
+    pub fn finish(&mut self, rates: &HashMap<(Currency, Currency), f64>) -> Option<(usize, Result<Solution, Error>)> {
+        let (number, statement) = self.pending.take()?;
+        self.statement = statement.split_whitespace().collect::<Vec<&str>>().join(" ");
+        Some((number, solve_line(statement, self.variables.clone(), self.definitions.clone(), rates, self.angle_mode)))
+    }
+
+    /// `solve` with the errors turned into the type `E` by `map_error`
+    fn solve_with<E>(&mut self, number: usize, line: &str, rates: &HashMap<(Currency, Currency), f64>, map_error: fn(Error) -> E) -> Option<Result<Solution, E>> {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            return None;
+        }
+
+        // the line after the start of a statement, its error positions are in this line
+        let (statement, offset, text) = match self.pending.take() {
+            Some((_, start)) => (format!("{} {}", start, line), start.chars().count() + 1, format!("{} {}", start.trim(), line.trim())),
+            None => (line.to_string(), 0, line.trim().to_string())
+        };
+        if continues(&statement) {
+            // the comment would hide the next line
+            self.pending = Some((number, without_comment(&statement).to_string()));
+            return None;
+        }
+        self.statement = text;
+
+        // `vars` lists the variables of the previous lines, it isn't a result for `ans` or `total`
+        if without_comment(&statement).trim() == "vars" {
+            let value = variables_listing(&self.variables.borrow());
+            return Some(Ok(Solution { kind: StatementKind::Expression, value }));
+        }
+
+        let (reads_total, assigns_total) = uses_variable(&statement, "total");
+        if !self.user_total {
+            match self.total {
+                Ok(ref sum) => self.variables.borrow_mut().insert(String::from("total"), sum.clone()),
+                // `10€` and `5£` without a rate: the line with `total` gets the error of the sum
+                Err(ref error) if reads_total => return Some(Err(map_error(error.clone()))),
+                Err(_) => self.variables.borrow_mut().remove("total")
+            };
+        }
+
+        let result = solve_line(statement, self.variables.clone(), self.definitions.clone(), rates, self.angle_mode)
+            .map_err(|error| match error {
+                Error::InvalidSyntax(pos) => Error::InvalidSyntax(pos.saturating_sub(offset)),
+                error => error
+            })
+            .map_err(map_error);
+        if let Ok(solution) = &result {
+            let res = &solution.value;
+            // the next lines can chain on this result, with `ans` or `line1`, `line2`...
+            let mut variables = self.variables.borrow_mut();
+            variables.insert(String::from("ans"), res.clone());
+            variables.insert(format!("line{}", number + 1), res.clone());
+            self.user_total |= assigns_total;
+
+            // only the numbers and the money are summed, a line with the total would count the lines before it twice
+            let number = matches!(res, ResType::Int(_) | ResType::Float(_) | ResType::Money(_, _) | ResType::Rational(_, _));
+            if number && !reads_total {
+                self.total = self.total.clone().and_then(|sum| total(&[sum, res.clone()], rates));
+            }
+        }
+
+        Some(result)
+    }
+}
+
+/// Add up `values`, like the results of the previous lines.
+/// The Money is converted to the currency of the first one.
+pub fn total(values: &[ResType], rates: &HashMap<(Currency, Currency), f64>) -> Result<ResType, Error> {
+    let mut sum = ResType::Int(0);
+    let mut currency = None;
+
+    for value in values {
+        let value = match (currency, value) {
+            (Some(to), ResType::Money(_, _)) => convert(value.clone(), to, rates)?,
+            (None, ResType::Money(_, from)) => {
+                currency = Some(*from);
+                value.clone()
+            },
+            (_, ResType::Bool(_) | ResType::Date(_) | ResType::Duration(_) | ResType::Quantity(_, _)) => return Err(Error::OutOfDomain),
+            _ => value.clone()
+        };
+        sum = sum.checked(value, i128::checked_add, ResType::add)?;
+    }
+
+    Ok(sum)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn make_interpreter(text: &str, variables: Option<Rc<RefCell<HashMap<String, ResType>>>>) -> Interpreter {
+        
+        // Create an empty variables array if none is defined
+        let vars = match variables {
+            Some(vars) => vars,
+            None => Rc::new(RefCell::new(HashMap::new()))
+        };
+
+        let lexer = Lexer::new(String::from(text));
+        let parser = Parser::new(lexer).expect("Could not parse");
+        let interpreter = Interpreter::new(parser, vars);
+
+        interpreter
+    }
+
+    /// Return all the tokens of `text`
+    fn tokenize(text: &str) -> Vec<Token> {
+        let mut lexer = Lexer::new(String::from(text));
+        let mut tokens = vec![];
+
+        loop {
+            match lexer.get_next_token().expect("Could not tokenize") {
+                Token::EOF => return tokens,
+                token => tokens.push(token)
+            }
+        }
+    }
+
+    #[test]
+    fn test_lexer_unicode() {
+        let tokens = tokenize("100€ + café ");
+        assert_eq!(tokens, vec![
+            Token::INTEGER(100),
+            Token::MONEY(Currency::Euro),
+            Token::PLUS,
+            Token::VAR(String::from("café")),
+        ]);
+
+        let tokens = tokenize("cafés+€2");
+        assert_eq!(tokens, vec![
+            Token::VAR(String::from("cafés")),
+            Token::PLUS,
+            Token::MONEY(Currency::Euro),
+            Token::INTEGER(2),
+        ]);
+    }
+
+    #[test]
+    fn test_lexer_long_input() {
+        // 10 000 chars: 1+1+1+...+1
+        let text = vec!["1"; 5000].join("+");
+
+        let start = std::time::Instant::now();
+        let tokens = tokenize(&text);
+        assert_eq!(tokens.len(), 9999);
+        assert!(start.elapsed() < std::time::Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_expression1() {
+        let mut interpreter = make_interpreter("3", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(3)));
+    }
+
+    #[test]
+    fn test_expression2() {
+        let mut interpreter = make_interpreter("2 + 7 * 4", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(30)));
+    }
+
+    #[test]
+    fn test_expression3() {
+        let mut interpreter = make_interpreter("7 - 8 / 4", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(5)));
+    }
+
+    #[test]
+    fn test_expression4() {
+        let mut interpreter = make_interpreter("14 + 2 * 3 - 6 / 2", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(17)));
+    }
+
+    #[test]
+    fn test_expression5() {
+        let mut interpreter = make_interpreter("7 + 3 * (10 / (12 / (3 + 1) - 1))", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(22)));
+    }
+
+    #[test]
+    fn test_expression6() {
+        let mut interpreter = make_interpreter(
+            "7 + 3 * (10 / (12 / (3 + 1) - 1)) / (2 + 3) - 5 - 3 + (8)", None
+        );
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(10)));
+    }
+
+    #[test]
+    fn test_expression7() {
+        let mut interpreter = make_interpreter("7 + (((3 + 2)))", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(12)));
+    }
+
+    #[test]
+    fn test_expression_invalid_syntax() {
+        let mut interpreter = make_interpreter("10 *", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Err(Error::InvalidSyntax(4)));
+    }
+
+    #[test]
+    fn test_expression_invalid_syntax_position() {
+        let mut interpreter = make_interpreter("3 + )", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Err(Error::InvalidSyntax(4)));
+
+        let lexer = Lexer::new(String::from("2 * 3 @ 4"));
+        let mut parser = Parser::new(lexer).unwrap();
+        assert_eq!(parser.parse().err(), Some(Error::InvalidSyntax(6)));
+    }
+
+    #[test]
+    fn test_solve_invalid_syntax_column() {
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+        let rates = HashMap::new();
+
+        let result = solve(String::from("10 * "), vars.clone(), &rates);
+        assert_eq!(result, Err(String::from("Invalid syntax at column 5")));
+
+        let result = solve(String::from("  3 + )"), vars, &rates);
+        assert_eq!(result, Err(String::from("Invalid syntax at column 7")));
+    }
+
+    #[test]
+    fn test_error_messages() {
+        assert_eq!(Error::InvalidSyntax(4).to_string(), "Invalid syntax at column 5");
+        assert_eq!(Error::UndefinedVariable(String::from("x"), None).to_string(), "Undefined variable: x");
+        assert_eq!(Error::UndefinedVariable(String::from("totl"), Some(String::from("total"))).to_string(),
+            "Undefined variable: totl, did you mean total?");
+        assert_eq!(Error::DivisonByZero.to_string(), "Division by zero");
+        assert_eq!(Error::IncorrectFloat.to_string(), "Incorrect number");
+        assert_eq!(Error::UnknownFunction.to_string(), "Unknown function");
+        assert_eq!(Error::WrongArgumentCount.to_string(), "Wrong number of arguments");
+        assert_eq!(Error::Overflow.to_string(), "Overflow");
+        assert_eq!(Error::NoConversionRate.to_string(), "No conversion rate");
+        assert_eq!(Error::OutOfDomain.to_string(), "Out of the function domain");
+        assert_eq!(Error::CircularReference.to_string(), "Circular reference");
+        assert_eq!(Error::IncompatibleUnits.to_string(), "Incompatible units");
+        assert_eq!(Error::ProtectedConstant.to_string(), "Constants can't be assigned");
+
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+        let result = solve(String::from("price * 2"), vars.clone(), &HashMap::new());
+        assert_eq!(result, Err(String::from("Undefined variable: price")));
+
+        let result = solve(String::from("1 / 0"), vars, &HashMap::new());
+        assert_eq!(result, Err(String::from("Division by zero")));
+    }
+
+    #[test]
+    fn test_expression_unary() {
+        let mut interpreter = make_interpreter("---42", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(-42)));
+    }
+
+    #[test]
+    fn test_expression_unary2() {
+        let mut interpreter = make_interpreter("-6*-7 - 3", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(39)));
+    }
+
+    #[test]
+    fn test_expression_variable1() {
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+
+        let mut interpreter = make_interpreter("a=5", Some(vars.clone()));
+        _ = interpreter.interpret();
+        let mut interpreter = make_interpreter("a", Some(vars));
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(5)));
+    }
+
+    #[test]
+    fn test_expression_variable2() {
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+
+        let mut interpreter = make_interpreter("bob=(525+83)/4", Some(vars.clone()));
+        _ = interpreter.interpret();
+        let mut interpreter = make_interpreter("bob + 48", Some(vars));
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(200)));
+    }
+
+    #[test]
+    fn test_expression_variable3() {
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+
+        let mut interpreter = make_interpreter("a=2", Some(vars.clone()));
+        _ = interpreter.interpret();
+        let mut interpreter = make_interpreter("b=1", Some(vars.clone()));
+        _ = interpreter.interpret();
+        let mut interpreter = make_interpreter("b=3", Some(vars.clone()));
+        _ = interpreter.interpret();
+        let mut interpreter = make_interpreter("a+b", Some(vars));
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(5)));
+    }
+
+    #[test]
+    fn test_variable_name_with_digits() {
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+
+        let mut interpreter = make_interpreter("var_1 = 5", Some(vars.clone()));
+        _ = interpreter.interpret();
+        let mut interpreter = make_interpreter("var_1 * 2", Some(vars.clone()));
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(10)));
+
+        let mut interpreter = make_interpreter("_total_2024 = 3", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(3)));
+
+        // a number followed by a name is an implicit multiplication
+        let mut interpreter = make_interpreter("a = 4", Some(vars.clone()));
+        _ = interpreter.interpret();
+        assert_eq!(tokenize("1a"), vec![Token::INTEGER(1), Token::VAR(String::from("a"))]);
+        let mut interpreter = make_interpreter("1a", Some(vars));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(4)));
+    }
+
+    #[test]
+    fn test_protect_constants() {
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+
+        let mut interpreter = make_interpreter("pi = 3", Some(vars.clone()));
+        interpreter.set_protect_constants(true);
+        assert_eq!(interpreter.interpret(), Err(Error::ProtectedConstant));
+        let mut interpreter = make_interpreter("pi", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Float(f64::consts::PI)));
+
+        let mut interpreter = make_interpreter("e += 1", Some(vars.clone()));
+        interpreter.set_protect_constants(true);
+        assert_eq!(interpreter.interpret(), Err(Error::ProtectedConstant));
+
+        // shadowed by default
+        let mut interpreter = make_interpreter("pi = 3", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(3)));
+        let mut interpreter = make_interpreter("pi", Some(vars));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(3)));
+    }
+
+    #[test]
+    fn test_delete_variable() {
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+
+        let mut interpreter = make_interpreter("x = 5", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(5)));
+        let mut interpreter = make_interpreter("del x", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(5)));
+        let mut interpreter = make_interpreter("x", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Err(Error::UndefinedVariable(String::from("x"), None)));
+
+        // nothing to delete
+        let mut interpreter = make_interpreter("del x", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Err(Error::UndefinedVariable(String::from("x"), None)));
+
+        let mut interpreter = make_interpreter("y = 2; del y; y", Some(vars));
+        assert_eq!(interpreter.interpret(), Err(Error::UndefinedVariable(String::from("y"), None)));
+
+        assert_eq!(tokenize("del x"), vec![Token::DEL, Token::VAR(String::from("x"))]);
+        let mut parser = Parser::new(Lexer::new(String::from("del 2"))).unwrap();
+        assert_eq!(parser.parse().err(), Some(Error::InvalidSyntax(4)));
+    }
+
+    #[test]
+    fn test_case_insensitive_variables() {
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+
+        let mut interpreter = make_interpreter("Rate = 5", Some(vars.clone()));
+        interpreter.set_case_insensitive(true);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(5)));
+
+        for text in ["rate", "RATE", "2 Rates", "rate += 1"] {
+            let mut interpreter = make_interpreter(text, Some(vars.clone()));
+            interpreter.set_case_insensitive(true);
+            assert!(interpreter.interpret().is_ok(), "{}", text);
+        }
+        assert_eq!(vars.borrow().get("rate"), Some(&ResType::Int(6)));
+
+        let mut interpreter = make_interpreter("2 Rates", Some(vars.clone()));
+        interpreter.set_case_insensitive(true);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(12)));
+
+        // case sensitive by default
+        let mut interpreter = make_interpreter("Price = 5", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(5)));
+        let mut interpreter = make_interpreter("price = 7", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(7)));
+        let mut interpreter = make_interpreter("Price", Some(vars));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(5)));
+    }
+
+    #[test]
+    fn test_variable_plurals() {
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+        vars.borrow_mut().insert(String::from("item"), ResType::Int(2));
+        vars.borrow_mut().insert(String::from("items"), ResType::Int(7));
+        vars.borrow_mut().insert(String::from("box"), ResType::Int(3));
+
+        // both are defined, the plural isn't the singular
+        let mut interpreter = make_interpreter("items", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(7)));
+        let mut interpreter = make_interpreter("item", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(2)));
+
+        let mut interpreter = make_interpreter("2 boxes", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(6)));
+
+        let mut interpreter = make_interpreter("boxes", Some(vars));
+        interpreter.set_plurals(false);
+        assert_eq!(interpreter.interpret(), Err(Error::UndefinedVariable(String::from("boxes"), Some(String::from("box")))));
+    }
+
+    #[test]
+    fn test_circular_reference() {
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+        let definitions = Rc::new(RefCell::new(HashMap::new()));
+
+        let mut interpreter = make_interpreter("a = b + 1", Some(vars.clone()));
+        interpreter.set_definitions(definitions.clone());
+        assert_eq!(interpreter.interpret(), Err(Error::UndefinedVariable(String::from("b"), None)));
+
+        let mut interpreter = make_interpreter("b = a + 1", Some(vars.clone()));
+        interpreter.set_definitions(definitions.clone());
+        assert_eq!(interpreter.interpret(), Err(Error::CircularReference));
+
+        // `a` is computed once `b` is defined
+        let mut interpreter = make_interpreter("b = 4", Some(vars.clone()));
+        interpreter.set_definitions(definitions.clone());
+        _ = interpreter.interpret();
+        let mut interpreter = make_interpreter("a * 2", Some(vars.clone()));
+        interpreter.set_definitions(definitions);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(10)));
+
+        // using the previous value isn't circular
+        let mut interpreter = make_interpreter("b = b + 1", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(5)));
+    }
+
+    #[test]
+    fn test_expression_line_reference() {
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+        vars.borrow_mut().insert(String::from("line1"), ResType::Int(5));
+
+        let mut interpreter = make_interpreter("line1 * 2", Some(vars.clone()));
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(10)));
+
+        // `lines` isn't the plural of a line reference, and a line reference isn't suggested
+        let mut interpreter = make_interpreter("lines", Some(vars));
+        let result = interpreter.interpret();
+        assert_eq!(result, Err(Error::UndefinedVariable(String::from("lines"), None)));
+    }
+
+    #[test]
+    fn test_comment() {
+        assert_eq!(tokenize("# just a note"), vec![]);
+        assert_eq!(tokenize("a# note"), vec![Token::VAR(String::from("a"))]);
+
+        let mut interpreter = make_interpreter("5 + 3 # note", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(8)));
+
+        let mut interpreter = make_interpreter("50 % # of the price", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(0.5)));
+    }
+
+    #[test]
+    fn test_solve_comment_only() {
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+
+        // nothing to compute, the result pane shows an empty line
+        let result = solve(String::from("  # groceries"), vars, &HashMap::new());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_statement_kind() {
+        let mut interpreter = make_interpreter("a = 5", None);
+        let result = interpreter.interpret_statement();
+        assert_eq!(result, Ok(Solution {
+            kind: StatementKind::Assignment { name: String::from("a") },
+            value: ResType::Int(5)
+        }));
+
+        let mut interpreter = make_interpreter("5 + 2", None);
+        let result = interpreter.interpret_statement();
+        assert_eq!(result, Ok(Solution { kind: StatementKind::Expression, value: ResType::Int(7) }));
+    }
+
+    #[test]
+    fn test_integer_division() {
+        assert_eq!(tokenize("7 // 2"), vec![Token::INTEGER(7), Token::INTDIV, Token::INTEGER(2)]);
+
+        let mut interpreter = make_interpreter("7 // 2", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(3)));
+
+        let mut interpreter = make_interpreter("-7 // 2", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(-4)));
+
+        let mut interpreter = make_interpreter("7.5 // 2", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(3)));
+
+        let mut interpreter = make_interpreter("10 // 0", None);
+        assert_eq!(interpreter.interpret(), Err(Error::DivisonByZero));
+    }
+
+    #[test]
+    fn test_bitwise() {
+        let mut interpreter = make_interpreter("12 & 10", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(8)));
+
+        let mut interpreter = make_interpreter("12 | 3", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(15)));
+
+        let mut interpreter = make_interpreter("6 xor 3", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(5)));
+
+        // below the arithmetic
+        let mut interpreter = make_interpreter("1 + 2 & 6", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(2)));
+
+        // the position of the operator
+        let mut interpreter = make_interpreter("1.5 & 2", None);
+        assert_eq!(interpreter.interpret(), Err(Error::InvalidSyntax(4)));
+
+        let mut interpreter = make_interpreter("12 | 3€", None);
+        assert_eq!(interpreter.interpret(), Err(Error::InvalidSyntax(3)));
+    }
+
+    #[test]
+    fn test_shift() {
+        let mut interpreter = make_interpreter("1 << 4", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(16)));
+
+        let mut interpreter = make_interpreter("256 >> 2", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(64)));
+
+        let mut interpreter = make_interpreter("1 << 2 + 1", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(8)));
+
+        let mut interpreter = make_interpreter("1 << -1", None);
+        assert_eq!(interpreter.interpret(), Err(Error::Overflow));
+
+        let mut interpreter = make_interpreter("1 << 127", None);
+        assert_eq!(interpreter.interpret(), Err(Error::Overflow));
+
+        // like the bitwise operators
+        let mut interpreter = make_interpreter("2.5 >> 1", None);
+        assert_eq!(interpreter.interpret(), Err(Error::InvalidSyntax(4)));
+    }
+
+    #[test]
+    fn test_compound_assignment() {
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+
+        let mut interpreter = make_interpreter("x = 10", Some(vars.clone()));
+        _ = interpreter.interpret();
+        let mut interpreter = make_interpreter("x += 5", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(15)));
+        let mut interpreter = make_interpreter("x *= 2", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(30)));
+        let mut interpreter = make_interpreter("x -= 6", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(24)));
+        let mut interpreter = make_interpreter("x /= 4", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(6)));
+
+        let mut interpreter = make_interpreter("y += 1", Some(vars));
+        assert_eq!(interpreter.interpret(), Err(Error::UndefinedVariable(String::from("y"), None)));
+    }
+
+    #[test]
+    fn test_multiple_statements() {
+        let mut interpreter = make_interpreter("a=2; b=3; a+b", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(5)));
+
+        let mut interpreter = make_interpreter("x=1; x+1", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(2)));
+
+        let mut interpreter = make_interpreter("x=1; y=x*4;", None);
+        assert_eq!(interpreter.interpret_statement(), Ok(Solution {
+            kind: StatementKind::Assignment { name: String::from("y") },
+            value: ResType::Int(4)
+        }));
+
+        let mut interpreter = make_interpreter("1; ;", None);
+        assert_eq!(interpreter.interpret(), Err(Error::InvalidSyntax(3)));
+    }
+
+    #[test]
+    fn test_rounding_functions() {
+        let mut interpreter = make_interpreter("abs(-5)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(5)));
+
+        let mut interpreter = make_interpreter("abs(-2.5€)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Money(2.5, Currency::Euro)));
+
+        let mut interpreter = make_interpreter("round(2.5)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(3)));
+
+        let mut interpreter = make_interpreter("round(-2.5)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(-3)));
+
+        let mut interpreter = make_interpreter("floor(2.9)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(2)));
+
+        let mut interpreter = make_interpreter("ceil(2.1)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(3)));
+
+        let mut interpreter = make_interpreter("floor(10.7€)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Money(10.0, Currency::Euro)));
+    }
+
+    #[test]
+    fn test_min_max() {
+        let mut interpreter = make_interpreter("min(3, 7, 1)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(1)));
+
+        let mut interpreter = make_interpreter("max(2.5, 2)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Float(2.5)));
+
+        let mut interpreter = make_interpreter("min(10€, 5€)", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "5.00 €");
+
+        let mut interpreter = make_interpreter("max(10€, 5$)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::NoConversionRate));
+
+        let mut interpreter = make_interpreter("max(10€, 5$)", None);
+        interpreter.add_rate(Currency::Euro, Currency::Dollar, 0.1);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Money(5.0, Currency::Dollar)));
+
+        let mut interpreter = make_interpreter("max(4)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::WrongArgumentCount));
+    }
+
+    #[test]
+    fn test_sign_trunc_clamp() {
+        let mut interpreter = make_interpreter("sign(-3)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(-1)));
+
+        let mut interpreter = make_interpreter("sign(0) + sign(2.5€)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(1)));
+
+        let mut interpreter = make_interpreter("trunc(2.9)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(2)));
+
+        let mut interpreter = make_interpreter("trunc(-2.9)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(-2)));
+
+        let mut interpreter = make_interpreter("clamp(15, 0, 10)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(10)));
+
+        let mut interpreter = make_interpreter("clamp(-2.5, 0, 10)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(0)));
+
+        let mut interpreter = make_interpreter("clamp(45€, 0€, 100€)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Money(45.0, Currency::Euro)));
+
+        let mut interpreter = make_interpreter("clamp(5, 10, 0)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+
+        let mut interpreter = make_interpreter("clamp(5, 10)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::WrongArgumentCount));
+    }
+
+    #[test]
+    fn test_combinatorics() {
+        let mut interpreter = make_interpreter("nCr(5, 2)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(10)));
+
+        let mut interpreter = make_interpreter("nPr(5, 2)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(20)));
+
+        let mut interpreter = make_interpreter("nCr(5, 0) + nCr(5, 5)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(2)));
+
+        let mut interpreter = make_interpreter("nCr(100, 50)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(100891344545564193334812497256)));
+
+        let mut interpreter = make_interpreter("nPr(100, 50)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::Overflow));
+
+        let mut interpreter = make_interpreter("nCr(2, 5)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::InvalidSyntax(0)));
+
+        let mut interpreter = make_interpreter("nCr(5, -1)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::InvalidSyntax(0)));
+
+        let mut interpreter = make_interpreter("1 + nPr(5.5, 2)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::InvalidSyntax(4)));
+
+        let mut interpreter = make_interpreter("nPr(5)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::WrongArgumentCount));
+    }
+
+    #[test]
+    fn test_gcd_lcm() {
+        let mut interpreter = make_interpreter("gcd(12, 18)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(6)));
+
+        let mut interpreter = make_interpreter("lcm(4, 6)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(12)));
+
+        let mut interpreter = make_interpreter("gcd(12, 8, 20)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(4)));
+
+        let mut interpreter = make_interpreter("gcd(-4, 6) + lcm(-4, 6)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(14)));
+
+        let mut interpreter = make_interpreter("gcd(0, 0) + lcm(0, 5)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(0)));
+
+        let mut interpreter = make_interpreter("lcm(2, 170141183460469231731687303715884105727)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::Overflow));
+
+        let mut interpreter = make_interpreter("gcd(4.5, 2)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+
+        let mut interpreter = make_interpreter("gcd(4€, 2€)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+
+        let mut interpreter = make_interpreter("lcm(4)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::WrongArgumentCount));
+    }
+
+    #[test]
+    fn test_undefined_variable_suggestion() {
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+        vars.borrow_mut().insert(String::from("subtotal"), ResType::Int(12));
+        vars.borrow_mut().insert(String::from("tax"), ResType::Int(2));
+
+        let mut interpreter = make_interpreter("subtotl * 2", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Err(Error::UndefinedVariable(String::from("subtotl"), Some(String::from("subtotal")))));
+
+        // the variables of the lines aren't suggested
+        vars.borrow_mut().insert(String::from("total"), ResType::Int(14));
+        vars.borrow_mut().insert(String::from("ans"), ResType::Int(14));
+        let mut interpreter = make_interpreter("totl", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Err(Error::UndefinedVariable(String::from("totl"), None)));
+        let mut interpreter = make_interpreter("an", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Err(Error::UndefinedVariable(String::from("an"), None)));
+
+        let mut interpreter = make_interpreter("tqx + 1", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Err(Error::UndefinedVariable(String::from("tqx"), Some(String::from("tax")))));
+
+        let mut interpreter = make_interpreter("price", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Err(Error::UndefinedVariable(String::from("price"), None)));
+
+        // a single letter is another variable, not a typo
+        let mut interpreter = make_interpreter("x", Some(vars));
+        assert_eq!(interpreter.interpret(), Err(Error::UndefinedVariable(String::from("x"), None)));
+
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("été", "ete"), 2);
+    }
+
+    #[test]
+    fn test_sum_avg() {
+        let mut interpreter = make_interpreter("sum(1,2,3)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(6)));
+
+        let mut interpreter = make_interpreter("avg(2,4)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(3)));
+
+        let mut interpreter = make_interpreter("avg(2,3)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Float(2.5)));
+
+        let mut interpreter = make_interpreter("sum(10€, 20€)", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "30.00 €");
+
+        let mut interpreter = make_interpreter("sum(10€, 20$)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::NoConversionRate));
+    }
+
+    #[test]
+    fn test_comparison() {
+        assert_eq!(tokenize("1 < 2 <= 3 > 4 >= 5 == 6 != 7 << 8 >> 9"), vec![
+            Token::INTEGER(1), Token::LT, Token::INTEGER(2), Token::LE, Token::INTEGER(3), Token::GT, Token::INTEGER(4),
+            Token::GE, Token::INTEGER(5), Token::EQ, Token::INTEGER(6), Token::NE, Token::INTEGER(7),
+            Token::SHL, Token::INTEGER(8), Token::SHR, Token::INTEGER(9)
+        ]);
+
+        let mut interpreter = make_interpreter("3 < 5", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Bool(true)));
+
+        let mut interpreter = make_interpreter("2 == 2.0", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Bool(true)));
+
+        let mut interpreter = make_interpreter("3! != 6", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Bool(false)));
+
+        let mut interpreter = make_interpreter("1 + 2 >= 3", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "true");
+
+        let mut interpreter = make_interpreter("10€ > 5€", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Bool(true)));
+
+        let mut interpreter = make_interpreter("10€ > 5$", None);
+        assert_eq!(interpreter.interpret(), Err(Error::NoConversionRate));
+
+        let mut interpreter = make_interpreter("(1 < 2) == (3 < 4)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Bool(true)));
+
+        let mut interpreter = make_interpreter("(1 < 2) + 1", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+
+        let mut interpreter = make_interpreter("(1 < 2) < 3", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+
+        let mut interpreter = make_interpreter("1 < 2 < 3", None);
+        assert!(matches!(interpreter.interpret(), Err(Error::InvalidSyntax(_))));
+    }
+
+    #[test]
+    fn test_ternary() {
+        let mut interpreter = make_interpreter("1 < 2 ? 5 : 10", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(5)));
+
+        let mut interpreter = make_interpreter("0 > 1 ? 1 : 2", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(2)));
+
+        // the other branch isn't computed
+        let mut interpreter = make_interpreter("1 < 2 ? 5 : 10/0", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(5)));
+
+        let mut interpreter = make_interpreter("x = 3; x > 5 ? 1 : x > 2 ? 2 : 3", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(2)));
+
+        let mut interpreter = make_interpreter("1 ? 2 : 3", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+
+        let mut interpreter = make_interpreter("1 < 2 ? 3", None);
+        assert!(matches!(interpreter.interpret(), Err(Error::InvalidSyntax(_))));
+    }
+
+    #[test]
+    fn test_rational() {
+        let mut interpreter = make_interpreter("1/3", None);
+        interpreter.set_rational(true);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Rational(1, 3)));
+
+        let mut interpreter = make_interpreter("1/3 + 1/6", None);
+        interpreter.set_rational(true);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "1/2");
+
+        let mut interpreter = make_interpreter("2/1", None);
+        interpreter.set_rational(true);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(2)));
+
+        let mut interpreter = make_interpreter("-4/6 * 3", None);
+        interpreter.set_rational(true);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(-2)));
+
+        let mut interpreter = make_interpreter("3/-4", None);
+        interpreter.set_rational(true);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Rational(-3, 4)));
+
+        let mut interpreter = make_interpreter("1/2 + 0.25", None);
+        interpreter.set_rational(true);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Float(0.75)));
+
+        let mut interpreter = make_interpreter("1/3", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Float(1.0 / 3.0)));
+    }
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(tokenize("2024-01-15"), vec![Token::DATE(19737)]);
+        assert_eq!(tokenize("2024-01"), vec![Token::INTEGER(2024), Token::MINUS, Token::INTEGER(1)]);
+
+        let mut interpreter = make_interpreter("2024-01-01 + 31", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "2024-02-01");
+
+        let mut interpreter = make_interpreter("2024-03-01 - 2024-02-01", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(29)));
+
+        let mut interpreter = make_interpreter("2023-03-01 - 1", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "2023-02-28");
+
+        let mut interpreter = make_interpreter("2000-02-29 < 2000-03-01", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Bool(true)));
+
+        let mut interpreter = make_interpreter("2024-01-01 * 2", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+
+        let mut interpreter = make_interpreter("3 + 2024-13-40", None);
+        assert_eq!(interpreter.interpret(), Err(Error::InvalidSyntax(4)));
+
+        let lexer = Lexer::new(String::from("2023-02-29"));
+        assert_eq!(Parser::new(lexer).err(), Some(Error::InvalidSyntax(0)));
+    }
+
+    #[test]
+    fn test_durations() {
+        assert_eq!(tokenize("90min 2h 30s 1.5d"), vec![
+            Token::DURATION(5400.0), Token::DURATION(7200.0), Token::DURATION(30.0), Token::DURATION(129600.0)
+        ]);
+        assert_eq!(tokenize("2 h"), vec![Token::INTEGER(2), Token::VAR(String::from("h"))]);
+
+        let mut interpreter = make_interpreter("1h + 30min", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "1.5h");
+
+        let mut interpreter = make_interpreter("2h * 3", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Duration(21600.0)));
+
+        let mut interpreter = make_interpreter("1h / 2", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "30min");
+
+        let mut interpreter = make_interpreter("100min", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "100min");
+
+        let mut interpreter = make_interpreter("2h / 30min", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Float(4.0)));
+
+        let mut interpreter = make_interpreter("-90s", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "-1.5min");
+
+        let mut interpreter = make_interpreter("1h + 5", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+
+        let mut interpreter = make_interpreter("1h > 59min", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Bool(true)));
+    }
+
+    #[test]
+    fn test_lengths() {
+        assert_eq!(tokenize("5km 2in 3mi"), vec![
+            Token::QUANTITY(5.0, Unit::Kilometer), Token::QUANTITY(2.0, Unit::Inch), Token::QUANTITY(3.0, Unit::Mile)
+        ]);
+
+        let mut interpreter = make_interpreter("100cm in m", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "1 m");
+
+        let mut interpreter = make_interpreter("1km + 500m", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Quantity(1.5, Unit::Kilometer)));
+
+        let mut interpreter = make_interpreter("5ft in cm", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "152.4 cm");
+
+        let mut interpreter = make_interpreter("1mi to km", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "1.609344 km");
+
+        let mut interpreter = make_interpreter("1ft in in", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Quantity(12.000000000000002, Unit::Inch)));
+
+        let mut interpreter = make_interpreter("2 * 3m", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Quantity(6.0, Unit::Meter)));
+
+        let mut interpreter = make_interpreter("1km > 900m", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Bool(true)));
+
+        let mut interpreter = make_interpreter("1m + 1h", None);
+        assert_eq!(interpreter.interpret(), Err(Error::IncompatibleUnits));
+
+        let mut interpreter = make_interpreter("1m + 1", None);
+        assert_eq!(interpreter.interpret(), Err(Error::IncompatibleUnits));
+
+        let mut interpreter = make_interpreter("5 in m", None);
+        assert_eq!(interpreter.interpret(), Err(Error::IncompatibleUnits));
+    }
+
+    #[test]
+    fn test_temperatures() {
+        let mut interpreter = make_interpreter("100C in F", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "212 F");
+
+        let mut interpreter = make_interpreter("32F in C", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "0 C");
+
+        let mut interpreter = make_interpreter("0C in K", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "273.15 K");
+
+        let mut interpreter = make_interpreter("-40C in F", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Quantity(-40.0, Unit::Fahrenheit)));
+
+        let mut interpreter = make_interpreter("-246.3C in K", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "26.85 K");
+
+        let mut interpreter = make_interpreter("0K in F", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "-459.67 F");
+
+        // below the absolute zero
+        let mut interpreter = make_interpreter("-300C in K", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+
+        for text in ["-1K", "-500F", "-300C", "-(1K)", "x = -300C", "floor(-459.6F)"] {
+            let mut interpreter = make_interpreter(text, None);
+            assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain), "{}", text);
+        }
+
+        let mut interpreter = make_interpreter("-273.15C", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Quantity(-273.15, Unit::Celsius)));
+
+        let mut interpreter = make_interpreter("-459.67F", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Quantity(-459.67, Unit::Fahrenheit)));
+
+        let mut interpreter = make_interpreter("-500F in C", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+
+        let mut interpreter = make_interpreter("20C > 60F", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Bool(true)));
+
+        let mut interpreter = make_interpreter("20C + 10C", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+
+        let mut interpreter = make_interpreter("20C in m", None);
+        assert_eq!(interpreter.interpret(), Err(Error::IncompatibleUnits));
+    }
+
+    #[test]
+    fn test_data_sizes() {
+        assert_eq!(tokenize("1KB 2KiB"), vec![Token::QUANTITY(1.0, Unit::Kilobyte), Token::QUANTITY(2.0, Unit::Kibibyte)]);
+
+        let mut interpreter = make_interpreter("1GB in MB", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "1000 MB");
+
+        let mut interpreter = make_interpreter("1GiB in MiB", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "1024 MiB");
+
+        let mut interpreter = make_interpreter("1024MiB in GiB", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "1 GiB");
+
+        let mut interpreter = make_interpreter("500MB + 500MB", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "1 GB");
+
+        let mut interpreter = make_interpreter("1KB in B", None);
+        interpreter.set_binary_sizes(true);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "1024 B");
+
+        let mut interpreter = make_interpreter("1KiB - 24B", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "1000 B");
+
+        let mut interpreter = make_interpreter("1GB > 900MiB", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Bool(true)));
+
+        let mut interpreter = make_interpreter("1GB + 1m", None);
+        assert_eq!(interpreter.interpret(), Err(Error::IncompatibleUnits));
+    }
+
+    #[test]
+    #[cfg(feature = "bigint")]
+    fn test_bigint() {
+        let mut interpreter = make_interpreter("40!", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "815915283247897734345611269596115894272000000000");
+
+        let mut interpreter = make_interpreter("170141183460469231731687303715884105727 + 1", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "170141183460469231731687303715884105728");
+
+        // back to an i128 when it fits
+        let mut interpreter = make_interpreter("40! / 39!", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(40)));
+
+        let mut interpreter = make_interpreter("25! * 25! > 50!", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Bool(false)));
+
+        let mut interpreter = make_interpreter("2^128", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "340282366920938463463374607431768211456");
+    }
+
+    #[test]
+    #[cfg(not(feature = "bigint"))]
+    fn test_overflow() {
+        let mut interpreter = make_interpreter("100000000000000000000 * 100000000000000000000", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Err(Error::Overflow));
+    }
+
+    #[test]
+    #[cfg(not(feature = "bigint"))]
+    fn test_overflow_add() {
+        let max = i128::MAX.to_string();
+
+        let mut interpreter = make_interpreter(&format!("{} + 1", max), None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Err(Error::Overflow));
+
+        let mut interpreter = make_interpreter(&format!("-{} - 2", max), None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Err(Error::Overflow));
+    }
+
+    #[test]
+    fn test_overflow_literal() {
+        let text = "1".repeat(50);
+
+        let lexer = Lexer::new(text.clone());
+        assert_eq!(Parser::new(lexer).err(), Some(Error::Overflow));
+
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+        assert!(solve(text, vars, &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_float() {
+        let mut interpreter = make_interpreter("4.0", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(4.0)));
+    }
+
+    #[test]
+    fn test_negative_float() {
+        let mut interpreter = make_interpreter("-16.0 + 4", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(-12.0)));
+    }
+
+    #[test]
+    fn test_scientific1() {
+        let mut interpreter = make_interpreter("1e6", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(1000000.0)));
+    }
+
+    #[test]
+    fn test_scientific2() {
+        let mut interpreter = make_interpreter("2.5e-3", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(0.0025)));
+    }
+
+    #[test]
+    fn test_scientific3() {
+        let mut interpreter = make_interpreter("1E2", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(100.0)));
+    }
+
+    #[test]
+    fn test_scientific4() {
+        let mut interpreter = make_interpreter("2e3", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(2000.0)));
+    }
+
+    #[test]
+    fn test_scientific_without_exponent() {
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+
+        // `3e` is `3 * e`, not an incomplete exponent
+        let mut interpreter = make_interpreter("e=2", Some(vars.clone()));
+        _ = interpreter.interpret();
+        let mut interpreter = make_interpreter("3e", Some(vars));
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(6)));
+    }
+
+    #[test]
+    fn test_digit_separator() {
+        let mut interpreter = make_interpreter("1_000_000", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(1000000)));
+    }
+
+    #[test]
+    fn test_digit_separator_float() {
+        let mut interpreter = make_interpreter("1_000.5", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(1000.5)));
+
+        let mut interpreter = make_interpreter("1_000.000_5", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(1000.0005)));
+    }
+
+    #[test]
+    fn test_digit_separator_invalid() {
+        for text in ["5_", "5__0", "5_.0", "5._0"] {
+            let lexer = Lexer::new(String::from(text));
+            assert!(Parser::new(lexer).is_err(), "{} should not parse", text);
+        }
+
+        // a leading underscore starts a name, not a number
+        assert_eq!(tokenize("_5"), vec![Token::VAR(String::from("_5"))]);
+    }
+
+    #[test]
+    fn test_hexadecimal() {
+        let mut interpreter = make_interpreter("0xff", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(255)));
+    }
+
+    #[test]
+    fn test_binary() {
+        let mut interpreter = make_interpreter("0b1010", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(10)));
+    }
+
+    #[test]
+    fn test_hexadecimal_add() {
+        let mut interpreter = make_interpreter("0x10 + 1", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(17)));
+    }
+
+    #[test]
+    fn test_hexadecimal_invalid() {
+        let lexer = Lexer::new(String::from("0x"));
+        assert!(Parser::new(lexer).is_err());
+
+        let lexer = Lexer::new(String::from("0xG"));
+        assert!(Parser::new(lexer).is_err());
+    }
+
+    #[test]
+    fn test_division1() {
+        let mut interpreter = make_interpreter("20/4", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(5)));
+    }
+
+    #[test]
+    fn test_division2() {
+        let mut interpreter = make_interpreter("-5/2", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(-2.5)));
+    }
+
+    #[test]
+    fn test_division_zero() {
+        let mut interpreter = make_interpreter("120/0", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Err(Error::DivisonByZero));
+    }
+
+    #[test]
+    fn test_modulo1() {
+        let mut interpreter = make_interpreter("17 % 5", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(2)));
+    }
+
+    #[test]
+    fn test_modulo2() {
+        let mut interpreter = make_interpreter("-7 % 3", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(2)));
+    }
+
+    #[test]
+    fn test_modulo_float() {
+        let mut interpreter = make_interpreter("10.5 % 3", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(1.5)));
+    }
+
+    #[test]
+    fn test_modulo_zero() {
+        let mut interpreter = make_interpreter("12 % 0", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Err(Error::DivisonByZero));
+    }
+
+    #[test]
+    fn test_constant_pi() {
+        let mut interpreter = make_interpreter("pi", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(f64::consts::PI)));
+
+        let mut interpreter = make_interpreter("π", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(f64::consts::PI)));
+    }
+
+    #[test]
+    fn test_constant_pi_mul() {
+        let mut interpreter = make_interpreter("2*pi", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(2.0 * f64::consts::PI)));
+    }
+
+    #[test]
+    fn test_constant_e() {
+        let mut interpreter = make_interpreter("e", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(f64::consts::E)));
+    }
+
+    #[test]
+    fn test_constant_shadowing() {
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+
+        let mut interpreter = make_interpreter("pi=3", Some(vars.clone()));
+        _ = interpreter.interpret();
+        let mut interpreter = make_interpreter("pi", Some(vars));
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(3)));
+    }
+
+    #[test]
+    fn test_sqrt1() {
+        let mut interpreter = make_interpreter("sqrt(16)", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(4)));
+    }
+
+    #[test]
+    fn test_sqrt2() {
+        let mut interpreter = make_interpreter("sqrt(2)", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(f64::consts::SQRT_2)));
+    }
+
+    #[test]
+    fn test_sqrt_negative() {
+        let mut interpreter = make_interpreter("sqrt(-1)", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Err(Error::OutOfDomain));
+    }
+
+    #[test]
+    fn test_sin() {
+        let mut interpreter = make_interpreter("sin(0)", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(0.0)));
+    }
+
+    #[test]
+    fn test_cos() {
+        let mut interpreter = make_interpreter("cos(0)", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(1.0)));
+    }
+
+    #[test]
+    fn test_tan() {
+        let mut interpreter = make_interpreter("tan(0)", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(0.0)));
+    }
+
+    #[test]
+    fn test_deg_rad() {
+        let mut interpreter = make_interpreter("deg(pi)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Float(180.0)));
+
+        let mut interpreter = make_interpreter("rad(180)", None);
+        interpreter.set_angle_mode(AngleMode::Degrees);
+        assert!((interpreter.interpret().unwrap().get_f64() - f64::consts::PI).abs() < 1e-12);
+
+        let mut interpreter = make_interpreter("sin(90)", None);
+        interpreter.set_angle_mode(AngleMode::Degrees);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Float(1.0)));
+
+        let mut interpreter = make_interpreter("sin(90)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Float(90f64.sin())));
+
+        // the mode of the state applies to every line
+        let mut state = LineState::default();
+        state.set_angle_mode(AngleMode::Degrees);
+        let rates = HashMap::new();
+        assert_eq!(state.solve(0, "x = cos(180)", &rates).unwrap().unwrap().value, ResType::Float(-1.0));
+        let mut copy = state.clone();
+        assert_eq!(copy.solve(1, "sin(90) + x", &rates).unwrap().unwrap().value, ResType::Float(0.0));
+    }
+
+    #[test]
+    fn test_hypot_atan2() {
+        let mut interpreter = make_interpreter("hypot(3, 4)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Float(5.0)));
+
+        let mut interpreter = make_interpreter("atan2(1, 1)", None);
+        assert!((interpreter.interpret().unwrap().get_f64() - f64::consts::FRAC_PI_4).abs() < 1e-12);
+
+        let mut interpreter = make_interpreter("atan2(1, -1)", None);
+        interpreter.angle_mode = AngleMode::Degrees;
+        assert!((interpreter.interpret().unwrap().get_f64() - 135.0).abs() < 1e-12);
+
+        let mut interpreter = make_interpreter("hypot(3)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::WrongArgumentCount));
+    }
+
+    #[test]
+    fn test_sin_degrees() {
+        let mut interpreter = make_interpreter("sin(90)", None);
+        interpreter.angle_mode = AngleMode::Degrees;
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(1.0)));
+    }
+
+    #[test]
+    fn test_ln() {
+        let mut interpreter = make_interpreter("ln(e)", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(1.0)));
+    }
+
+    #[test]
+    fn test_cbrt_root() {
+        let mut interpreter = make_interpreter("cbrt(27)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(3)));
+
+        let mut interpreter = make_interpreter("cbrt(-8)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(-2)));
+
+        let mut interpreter = make_interpreter("root(16, 4)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(2)));
+
+        let mut interpreter = make_interpreter("root(-8, 3)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(-2)));
+
+        let mut interpreter = make_interpreter("root(2, 2)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Float(2f64.sqrt())));
+
+        let mut interpreter = make_interpreter("root(-4, 2)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+
+        let mut interpreter = make_interpreter("root(4, 0)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+    }
+
+    #[test]
+    fn test_complex() {
+        assert_eq!(tokenize("4i 2.5i 2 i"), vec![Token::IMAGINARY(4.0), Token::IMAGINARY(2.5), Token::INTEGER(2), Token::VAR(String::from("i"))]);
+
+        let mut interpreter = make_interpreter("(1+2i) + (3+4i)", None);
+        let res = interpreter.interpret();
+        assert_eq!(res, Ok(ResType::Complex(4.0, 6.0)));
+        assert_eq!(res.unwrap().to_string(), "4+6i");
+
+        // the imaginary part cancels out
+        let mut interpreter = make_interpreter("(1+i)*(1-i)", None);
+        let res = interpreter.interpret();
+        assert_eq!(res, Ok(ResType::Int(2)));
+        assert_eq!(res.unwrap().to_string(), "2");
+
+        let mut interpreter = make_interpreter("abs(3+4i)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(5)));
+
+        let mut interpreter = make_interpreter("conj(3+4i)", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "3-4i");
+
+        let mut interpreter = make_interpreter("(2+4i) / (1+i)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Complex(3.0, 1.0)));
+
+        let mut interpreter = make_interpreter("i^2", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(-1)));
+
+        let mut interpreter = make_interpreter("-2.5i", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "-2.5i");
+
+        let mut interpreter = make_interpreter("(1+i) / 0", None);
+        assert_eq!(interpreter.interpret(), Err(Error::DivisonByZero));
+
+        let mut interpreter = make_interpreter("(1+i) < 2", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+
+        let mut interpreter = make_interpreter("sqrt(2i)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+
+        let mut interpreter = make_interpreter("5€ + i", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+
+        // a variable `i` is still a variable
+        let vars = Rc::new(RefCell::new(HashMap::new()));
+        let mut interpreter = make_interpreter("i = 3", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(3)));
+        let mut interpreter = make_interpreter("i * 2", Some(vars));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(6)));
+    }
+
+    #[test]
+    fn test_change() {
+        let mut interpreter = make_interpreter("change(100, 120)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Float(20.0)));
+
+        let mut interpreter = make_interpreter("change(120, 90)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Float(-25.0)));
+
+        let mut interpreter = make_interpreter("change(40€, 50€)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Float(25.0)));
+
+        let mut interpreter = make_interpreter("change(0, 5)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::DivisonByZero));
+    }
+
+    #[test]
+    fn test_random() {
+        for _ in 0..100 {
+            let mut interpreter = make_interpreter("random()", None);
+            match interpreter.interpret() {
+                Ok(ResType::Float(val)) => assert!((0.0..1.0).contains(&val)),
+                result => panic!("random() gave {:?}", result)
+            }
 
-    match Parser::new(lexer) {
-        Ok(parser) => {
-            let mut interpreter = Interpreter::new(parser, variables);
+            let mut interpreter = make_interpreter("randint(1, 6)", None);
             match interpreter.interpret() {
-                Ok(result) => {
-                    Ok(format!("{}", result))
-                },
-                Err(_) => Err("Invalid syntax".to_string())
+                Ok(ResType::Int(val)) => assert!((1..=6).contains(&val)),
+                result => panic!("randint(1, 6) gave {:?}", result)
             }
-        },
-        Err(_) => Err("Invalid syntax".to_string())
-    }
-}
+        }
 
-#[allow(unused)]
-fn main() {
-    let variables: Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+        let mut interpreter = make_interpreter("randint(3, 3)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(3)));
 
-    loop {
-        // show the interactive prompt
-        print!("calc> ");
-        let mut input = String::new();
-        io::stdout().flush().unwrap();
-    
-        // read input from user
-    
-        io::stdin()
-            .read_line(&mut input)
-            .expect("Failed to read line");
+        let mut interpreter = make_interpreter("randint(6, 1)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
 
-        if input.eq("") || input.eq("exit\n") {
-            break;
-        }
+        let mut interpreter = make_interpreter("random(2)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::WrongArgumentCount));
 
-        match solve(input, variables.clone()) {
-            Ok(result) => println!("{}", result),
-            Err(_) => println!("Invalid syntax")
-        }
+        // the other functions still need their arguments
+        let mut interpreter = make_interpreter("sqrt()", None);
+        assert_eq!(interpreter.interpret(), Err(Error::WrongArgumentCount));
+
+        let mut interpreter = make_interpreter("avg()", None);
+        assert_eq!(interpreter.interpret(), Err(Error::WrongArgumentCount));
     }
-}
 
+    #[test]
+    fn test_exp() {
+        let mut interpreter = make_interpreter("exp(0)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Float(1.0)));
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let mut interpreter = make_interpreter("exp(1)", None);
+        assert!((interpreter.interpret().unwrap().get_f64() - f64::consts::E).abs() < 1e-12);
 
-    fn make_interpreter(text: &str, variables: Option<Rc<RefCell<HashMap<String, ResType>>>>) -> Interpreter {
-        
-        // Create an empty variables array if none is defined
-        let vars = match variables {
-            Some(vars) => vars,
-            None => Rc::new(RefCell::new(HashMap::new()))
-        };
+        let mut interpreter = make_interpreter("exp(1000)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::Overflow));
+    }
 
-        let lexer = Lexer::new(String::from(text));
-        let parser = Parser::new(lexer).expect("Could not parse");
-        let interpreter = Interpreter::new(parser, vars);
+    #[test]
+    fn test_pow_function() {
+        let mut interpreter = make_interpreter("pow(2, 10)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(1024)));
+
+        let mut interpreter = make_interpreter("pow(2, -1)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Float(0.5)));
+
+        let mut interpreter = make_interpreter("pow(2)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::WrongArgumentCount));
+        // the same types as `^`
+        for (function, operator) in [("pow(2m, 2)", "2m^2"), ("pow(2024-01-01, 1)", "2024-01-01^1"), ("pow(3>2, 2)", "(3>2)^2"), ("pow(i, 2)", "i^2")] {
+            let mut interpreter = make_interpreter(function, None);
+            let mut expected = make_interpreter(operator, None);
+            assert_eq!(interpreter.interpret(), expected.interpret(), "{}", function);
+        }
 
-        interpreter
+        let mut interpreter = make_interpreter("pow(2m, 2)", None);
+        assert!(interpreter.interpret().is_err());
+        let mut interpreter = make_interpreter("pow(i, 2)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(-1)));
     }
 
     #[test]
-    fn test_expression1() {
-        let mut interpreter = make_interpreter("3", None);
+    fn test_log() {
+        let mut interpreter = make_interpreter("log(1000)", None);
         let result = interpreter.interpret();
-        assert_eq!(result, Ok(ResType::Int(3)));
+        assert_eq!(result, Ok(ResType::Float(3.0)));
     }
 
     #[test]
-    fn test_expression2() {
-        let mut interpreter = make_interpreter("2 + 7 * 4", None);
+    fn test_log2() {
+        let mut interpreter = make_interpreter("log2(8)", None);
         let result = interpreter.interpret();
-        assert_eq!(result, Ok(ResType::Int(30)));
+        assert_eq!(result, Ok(ResType::Float(3.0)));
     }
 
     #[test]
-    fn test_expression3() {
-        let mut interpreter = make_interpreter("7 - 8 / 4", None);
-        let result = interpreter.interpret();
-        assert_eq!(result, Ok(ResType::Int(5)));
+    fn test_log_base() {
+        let mut interpreter = make_interpreter("log(8, 2)", None);
+        let result = interpreter.interpret().unwrap();
+        assert!((result.get_f64() - 3.0).abs() < 1e-12);
     }
 
     #[test]
-    fn test_expression4() {
-        let mut interpreter = make_interpreter("14 + 2 * 3 - 6 / 2", None);
+    fn test_log_domain() {
+        let mut interpreter = make_interpreter("ln(0)", None);
         let result = interpreter.interpret();
-        assert_eq!(result, Ok(ResType::Int(17)));
+        assert_eq!(result, Err(Error::OutOfDomain));
+
+        let mut interpreter = make_interpreter("log(-10)", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Err(Error::OutOfDomain));
     }
 
     #[test]
-    fn test_expression5() {
-        let mut interpreter = make_interpreter("7 + 3 * (10 / (12 / (3 + 1) - 1))", None);
+    fn test_wrong_argument_count() {
+        let mut interpreter = make_interpreter("sqrt(4, 2)", None);
         let result = interpreter.interpret();
-        assert_eq!(result, Ok(ResType::Int(22)));
+        assert_eq!(result, Err(Error::WrongArgumentCount));
     }
 
     #[test]
-    fn test_expression6() {
-        let mut interpreter = make_interpreter(
-            "7 + 3 * (10 / (12 / (3 + 1) - 1)) / (2 + 3) - 5 - 3 + (8)", None
-        );
+    fn test_unknown_function() {
+        let mut interpreter = make_interpreter("foo(1)", None);
         let result = interpreter.interpret();
-        assert_eq!(result, Ok(ResType::Int(10)));
+        assert_eq!(result, Err(Error::UnknownFunction));
     }
 
     #[test]
-    fn test_expression7() {
-        let mut interpreter = make_interpreter("7 + (((3 + 2)))", None);
+    fn test_factorial() {
+        let mut interpreter = make_interpreter("5!", None);
         let result = interpreter.interpret();
-        assert_eq!(result, Ok(ResType::Int(12)));
+        assert_eq!(result, Ok(ResType::Int(120)));
     }
 
     #[test]
-    fn test_expression_invalid_syntax() {
-        let mut interpreter = make_interpreter("10 *", None);
+    fn test_factorial_zero() {
+        let mut interpreter = make_interpreter("0!", None);
         let result = interpreter.interpret();
-        assert_eq!(result, Err(Error::InvalidSyntax));
+        assert_eq!(result, Ok(ResType::Int(1)));
     }
 
     #[test]
-    fn test_expression_unary() {
-        let mut interpreter = make_interpreter("---42", None);
-        let result = interpreter.interpret();
-        assert_eq!(result, Ok(ResType::Int(-42)));
+    fn test_thousands_separators() {
+        assert_eq!(tokenize("$1,234.56"), vec![Token::MONEY(Currency::Dollar), Token::FLOAT(1234.56)]);
+        assert_eq!(tokenize("1,234,567"), vec![Token::INTEGER(1234567)]);
+
+        let mut interpreter = make_interpreter("1,000 + 1", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(1001)));
+        let mut interpreter = make_interpreter("(1,000 + 1) * 2", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(2002)));
+
+        // the commas of a function call separate its arguments
+        let mut interpreter = make_interpreter("max(1,234)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(234)));
+        let mut interpreter = make_interpreter("max(2, 1,000)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(2)));
+        let mut interpreter = make_interpreter("max(2, (1,000))", None);
+        assert!(interpreter.interpret().is_err());
+
+        // not groups of thousands
+        assert_eq!(tokenize("1,23"), vec![Token::INTEGER(1), Token::COMMA, Token::INTEGER(23)]);
+        assert_eq!(tokenize("1,2345"), vec![Token::INTEGER(1), Token::COMMA, Token::INTEGER(2345)]);
+        assert_eq!(tokenize("1234,567"), vec![Token::INTEGER(1234), Token::COMMA, Token::INTEGER(567)]);
+        assert_eq!(tokenize("1.5,000"), vec![Token::FLOAT(1.5), Token::COMMA, Token::INTEGER(0)]);
+        let mut interpreter = make_interpreter("1,23", None);
+        assert_eq!(interpreter.interpret(), Err(Error::InvalidSyntax(1)));
     }
 
     #[test]
-    fn test_expression_unary2() {
-        let mut interpreter = make_interpreter("-6*-7 - 3", None);
+    fn test_power() {
+        let cases = [
+            ("2^10", ResType::Int(1024)),
+            ("-2^2", ResType::Int(-4)),
+            ("(-2)^2", ResType::Int(4)),
+            ("2^-2", ResType::Float(0.25)),
+            ("2^3^2", ResType::Int(512)),
+            ("2 * 3^2", ResType::Int(18)),
+            ("2(3)^2", ResType::Int(18)),
+            ("4^0.5", ResType::Float(2.0))
+        ];
+        for (text, expected) in cases {
+            let mut interpreter = make_interpreter(text, None);
+            assert_eq!(interpreter.interpret(), Ok(expected), "{}", text);
+        }
+
+        let mut interpreter = make_interpreter("2^-2", None);
+        interpreter.set_rational(true);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Rational(1, 4)));
+
+        let mut interpreter = make_interpreter("0^-1", None);
+        assert_eq!(interpreter.interpret(), Err(Error::DivisonByZero));
+        let mut interpreter = make_interpreter("(-8)^0.5", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+        let mut interpreter = make_interpreter("5€^2", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+        assert_eq!(tokenize("2^3"), vec![Token::INTEGER(2), Token::POW, Token::INTEGER(3)]);
+    }
+
+    #[test]
+    #[cfg(not(feature = "bigint"))]
+    fn test_power_overflow() {
+        let mut interpreter = make_interpreter("2^127", None);
+        assert_eq!(interpreter.interpret(), Err(Error::Overflow));
+        let mut interpreter = make_interpreter("10.0^400", None);
+        assert_eq!(interpreter.interpret(), Err(Error::Overflow));
+    }
+
+    #[test]
+    fn test_factorial_parenthesis() {
+        let mut interpreter = make_interpreter("(2+1)!", None);
         let result = interpreter.interpret();
-        assert_eq!(result, Ok(ResType::Int(39)));
+        assert_eq!(result, Ok(ResType::Int(6)));
     }
 
     #[test]
-    fn test_expression_variable1() {
-        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+    fn test_factorial_invalid() {
+        let mut interpreter = make_interpreter("-3!", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Err(Error::OutOfDomain));
 
-        let mut interpreter = make_interpreter("a=5", Some(vars.clone()));
-        _ = interpreter.interpret();
-        let mut interpreter = make_interpreter("a", Some(vars));
+        let mut interpreter = make_interpreter("3.5!", None);
         let result = interpreter.interpret();
-        assert_eq!(result, Ok(ResType::Int(5)));
+        assert_eq!(result, Err(Error::OutOfDomain));
     }
 
     #[test]
-    fn test_expression_variable2() {
-        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+    #[cfg(not(feature = "bigint"))]
+    fn test_factorial_overflow() {
+        let mut interpreter = make_interpreter("33!", None);
+        let result = interpreter.interpret();
+        assert!(result.is_ok());
 
-        let mut interpreter = make_interpreter("bob=(525+83)/4", Some(vars.clone()));
-        _ = interpreter.interpret();
-        let mut interpreter = make_interpreter("bob + 48", Some(vars));
+        let mut interpreter = make_interpreter("34!", None);
         let result = interpreter.interpret();
-        assert_eq!(result, Ok(ResType::Int(200)));
+        assert_eq!(result, Err(Error::Overflow));
     }
 
     #[test]
-    fn test_expression_variable3() {
-        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+    fn test_percent_of() {
+        let mut interpreter = make_interpreter("20% of 100", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(20)));
+    }
 
-        let mut interpreter = make_interpreter("a=2", Some(vars.clone()));
-        _ = interpreter.interpret();
-        let mut interpreter = make_interpreter("b=1", Some(vars.clone()));
-        _ = interpreter.interpret();
-        let mut interpreter = make_interpreter("b=3", Some(vars.clone()));
-        _ = interpreter.interpret();
-        let mut interpreter = make_interpreter("a+b", Some(vars));
+    #[test]
+    fn test_percent() {
+        let mut interpreter = make_interpreter("50%", None);
         let result = interpreter.interpret();
-        assert_eq!(result, Ok(ResType::Int(5)));
+        assert_eq!(result, Ok(ResType::Float(0.5)));
     }
 
     #[test]
-    fn test_float() {
-        let mut interpreter = make_interpreter("4.0", None);
+    fn test_percent_of_money() {
+        let mut interpreter = make_interpreter("10% of 250€", None);
         let result = interpreter.interpret();
-        assert_eq!(result, Ok(ResType::Float(4.0)));
+        assert_eq!(result, Ok(ResType::Money(25.0, Currency::Euro)));
     }
 
     #[test]
-    fn test_negative_float() {
-        let mut interpreter = make_interpreter("-16.0 + 4", None);
+    fn test_percent_is_not_modulo() {
+        let mut interpreter = make_interpreter("(50%) * 4", None);
         let result = interpreter.interpret();
-        assert_eq!(result, Ok(ResType::Float(-12.0)));
+        assert_eq!(result, Ok(ResType::Float(2.0)));
+
+        let mut interpreter = make_interpreter("17 % 5 + 1", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Int(3)));
+
+        let mut interpreter = make_interpreter("x = 10%; x", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Float(0.1)));
+
+        let mut interpreter = make_interpreter("50% < 1", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Bool(true)));
+
+        let mut interpreter = make_interpreter("50% == 0.5 ? 1 : 0", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(1)));
+
+        let mut interpreter = make_interpreter("17 % -5", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(2)));
+
+        let mut interpreter = make_interpreter("x = 17; y = 5; x % -y", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(2)));
+
+        let mut interpreter = make_interpreter("17 % +5", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(2)));
+
+        let mut interpreter = make_interpreter("50%-10", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Float(-9.5)));
     }
 
     #[test]
-    fn test_division1() {
-        let mut interpreter = make_interpreter("20/4", None);
+    fn test_percent_increase() {
+        let mut interpreter = make_interpreter("100 + 10%", None);
         let result = interpreter.interpret();
-        assert_eq!(result, Ok(ResType::Int(5)));
+        assert_eq!(result, Ok(ResType::Int(110)));
     }
 
     #[test]
-    fn test_division2() {
-        let mut interpreter = make_interpreter("-5/2", None);
+    fn test_percent_decrease() {
+        let mut interpreter = make_interpreter("100 - 10%", None);
         let result = interpreter.interpret();
-        assert_eq!(result, Ok(ResType::Float(-2.5)));
+        assert_eq!(result, Ok(ResType::Int(90)));
     }
 
     #[test]
-    fn test_division_zero() {
-        let mut interpreter = make_interpreter("120/0", None);
+    fn test_percent_increase_money() {
+        let mut interpreter = make_interpreter("80€ + 25%", None);
         let result = interpreter.interpret();
-        assert_eq!(result, Err(Error::DivisonByZero));
+        assert_eq!(result, Ok(ResType::Money(100.0, Currency::Euro)));
+
+        let mut interpreter = make_interpreter("50€ + 20%", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Money(60.0, Currency::Euro)));
+    }
+
+    #[test]
+    fn test_percent_mul() {
+        let mut interpreter = make_interpreter("50 * 10%", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Float(5.0)));
     }
 
     #[test]
@@ -1080,6 +5394,93 @@ mod tests {
         assert_eq!(result, Ok(ResType::Money(47.0, Currency::Dollar)));
     }
 
+    #[test]
+    fn test_money_pound() {
+        let mut interpreter = make_interpreter("£10", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Money(10.0, Currency::Pound)));
+    }
+
+    #[test]
+    fn test_money_yen() {
+        let mut interpreter = make_interpreter("¥500", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Money(500.0, Currency::Yen)));
+    }
+
+    #[test]
+    fn test_money_bitcoin() {
+        let mut interpreter = make_interpreter("0.5 BTC", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Money(0.5, Currency::Bitcoin)));
+
+        let mut interpreter = make_interpreter("₿0.25", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Money(0.25, Currency::Bitcoin)));
+
+        let mut interpreter = make_interpreter("0.1 BTC + 0.2 BTC", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "0.30000000 ₿");
+
+        let mut interpreter = make_interpreter("0.5 BTC in $", None);
+        interpreter.add_rate(Currency::Bitcoin, Currency::Dollar, 60000.0);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Money(30000.0, Currency::Dollar)));
+    }
+
+    #[test]
+    fn test_money_pound_add() {
+        let mut interpreter = make_interpreter("£5 + £5", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Money(10.0, Currency::Pound)));
+    }
+
+    #[test]
+    fn test_money_display() {
+        assert_eq!(ResType::Money(500.0, Currency::Yen).to_string(), "¥500");
+        assert_eq!(ResType::Money(10.0, Currency::Pound).to_string(), "£10.00");
+    }
+
+    #[test]
+    fn test_money_symbol_placement() {
+        let before = FormatOptions { symbol_placement: Some(SymbolPlacement::Before), ..Default::default() };
+        let after = FormatOptions { symbol_placement: Some(SymbolPlacement::After), ..Default::default() };
+
+        // the usual placement of each currency
+        assert_eq!(ResType::Money(100.0, Currency::Dollar).to_string(), "$100.00");
+        assert_eq!(ResType::Money(100.0, Currency::Euro).to_string(), "100.00 €");
+
+        assert_eq!(ResType::Money(100.0, Currency::Dollar).format(&before), "$100.00");
+        assert_eq!(ResType::Money(100.0, Currency::Dollar).format(&after), "100.00 $");
+        assert_eq!(ResType::Money(100.0, Currency::Euro).format(&before), "€100.00");
+        assert_eq!(ResType::Money(100.0, Currency::Euro).format(&after), "100.00 €");
+        assert_eq!(ResType::Money(-5.0, Currency::Euro).format(&before), "-€5.00");
+    }
+
+    #[test]
+    fn test_money_default_currency() {
+        let mut interpreter = make_interpreter("10€ + 5$", None);
+        interpreter.add_rate(Currency::Euro, Currency::Dollar, 1.25);
+        interpreter.set_default_currency(Some(Currency::Dollar));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Money(17.5, Currency::Dollar)));
+
+        // a single currency isn't converted
+        let mut interpreter = make_interpreter("10€ * 2", None);
+        interpreter.set_default_currency(Some(Currency::Dollar));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Money(20.0, Currency::Euro)));
+    }
+
+    #[test]
+    fn test_money_negative_style() {
+        let mut interpreter = make_interpreter("-500€", None);
+        let result = interpreter.interpret().unwrap();
+
+        assert_eq!(result.format(&FormatOptions::default()), "-500.00 €");
+
+        let accounting = FormatOptions { negative_money: NegativeMoney::Parentheses, ..Default::default() };
+        assert_eq!(result.format(&accounting), "(500.00 €)");
+        assert_eq!(ResType::Money(-1234.5, Currency::Dollar).format(&FormatOptions { thousands_separator: Some(','), ..accounting }), "($1,234.50)");
+        // only the money is in parentheses
+        assert_eq!(ResType::Money(500.0, Currency::Euro).format(&accounting), "500.00 €");
+        assert_eq!(ResType::Int(-500).format(&accounting), "-500");
+    }
+
     #[test]
     fn test_money_add() {
         let mut interpreter = make_interpreter("22€ + 8", None);
@@ -1108,6 +5509,221 @@ mod tests {
         assert_eq!(result, Ok(ResType::Money(6.25, Currency::Euro)));
     }
 
+    #[test]
+    fn test_money_rounding() {
+        let solve = |text: &str, rounding: MoneyRounding| {
+            let mut interpreter = make_interpreter(text, None);
+            interpreter.set_money_rounding(rounding);
+            interpreter.interpret()
+        };
+
+        assert_eq!(solve("10€ / 3", MoneyRounding::HalfUp), Ok(ResType::Money(3.33, Currency::Euro)));
+        assert_eq!(solve("10€ / 3", MoneyRounding::HalfEven), Ok(ResType::Money(3.33, Currency::Euro)));
+        assert_eq!(solve("20€ / 3", MoneyRounding::HalfUp), Ok(ResType::Money(6.67, Currency::Euro)));
+        assert_eq!(solve("20€ / 3", MoneyRounding::Floor), Ok(ResType::Money(6.66, Currency::Euro)));
+
+        // the halves
+        assert_eq!(solve("0.25€ / 2", MoneyRounding::HalfUp), Ok(ResType::Money(0.13, Currency::Euro)));
+        assert_eq!(solve("0.25€ / 2", MoneyRounding::HalfEven), Ok(ResType::Money(0.12, Currency::Euro)));
+        assert_eq!(solve("1.005€ * 1", MoneyRounding::HalfUp), Ok(ResType::Money(1.01, Currency::Euro)));
+
+        assert_eq!(solve("¥100 / 3", MoneyRounding::HalfUp), Ok(ResType::Money(33.0, Currency::Yen)));
+        assert_eq!(solve("10€ / 3", MoneyRounding::Exact), Ok(ResType::Money(10.0 / 3.0, Currency::Euro)));
+    }
+
+    #[test]
+    fn test_money_vat() {
+        let mut interpreter = make_interpreter("100€ + vat(20%)", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "120.00 €");
+
+        let mut interpreter = make_interpreter("net(120€, 20%)", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "100.00 €");
+
+        let mut interpreter = make_interpreter("$50 - vat(10%)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Money(45.0, Currency::Dollar)));
+
+        // 20% less than the price, not the price before the tax
+        let mut interpreter = make_interpreter("120€ - vat(20%)", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "96.00 €");
+
+        // the tax is always added to the price on its left
+        let mut interpreter = make_interpreter("vat(5.5%)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+
+        let mut interpreter = make_interpreter("vat(20%) + 100€", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+
+        let mut interpreter = make_interpreter("v = vat(20%)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::OutOfDomain));
+
+        let mut interpreter = make_interpreter("100€ + vat(20%, 5%)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::WrongArgumentCount));
+
+        let mut interpreter = make_interpreter("net(120€, -100%)", None);
+        assert_eq!(interpreter.interpret(), Err(Error::DivisonByZero));
+    }
+
+    #[test]
+    fn test_money_conversion() {
+        let mut interpreter = make_interpreter("10€ + 5$", None);
+        interpreter.add_rate(Currency::Euro, Currency::Dollar, 1.25);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Money(14.0, Currency::Euro)));
+
+        let mut interpreter = make_interpreter("5$ + 10€", None);
+        interpreter.add_rate(Currency::Euro, Currency::Dollar, 1.25);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Money(17.5, Currency::Dollar)));
+    }
+
+    #[test]
+    fn test_money_no_conversion_rate() {
+        let mut interpreter = make_interpreter("10€ + £5", None);
+        interpreter.add_rate(Currency::Euro, Currency::Dollar, 1.25);
+        let result = interpreter.interpret();
+        assert_eq!(result, Err(Error::NoConversionRate));
+    }
+
+    #[test]
+    fn test_money_conversion_in() {
+        let mut interpreter = make_interpreter("10€ in $", None);
+        interpreter.add_rate(Currency::Euro, Currency::Dollar, 1.25);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Money(12.5, Currency::Dollar)));
+    }
+
+    #[test]
+    fn test_money_conversion_to() {
+        let mut interpreter = make_interpreter("100$ to €", None);
+        interpreter.add_rate(Currency::Euro, Currency::Dollar, 1.25);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Money(80.0, Currency::Euro)));
+    }
+
+    #[test]
+    fn test_money_conversion_no_rate() {
+        let mut interpreter = make_interpreter("10€ in ¥", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Err(Error::NoConversionRate));
+    }
+
+    #[test]
+    fn test_money_code() {
+        assert_eq!(tokenize("100 USD"), vec![Token::INTEGER(100), Token::MONEY(Currency::Dollar)]);
+
+        let mut interpreter = make_interpreter("100 USD", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Money(100.0, Currency::Dollar)));
+
+        let mut interpreter = make_interpreter("50 EUR + 10 EUR", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Money(60.0, Currency::Euro)));
+
+        let mut interpreter = make_interpreter("GBP 20", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Money(20.0, Currency::Pound)));
+
+        let mut interpreter = make_interpreter("10 EUR in USD", None);
+        interpreter.add_rate(Currency::Euro, Currency::Dollar, 1.25);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Money(12.5, Currency::Dollar)));
+
+        // an unknown code is a variable
+        let mut interpreter = make_interpreter("100 XYZ", None);
+        assert_eq!(interpreter.interpret(), Err(Error::UndefinedVariable(String::from("XYZ"), None)));
+    }
+
+    #[test]
+    fn test_money_conversion_number() {
+        let mut interpreter = make_interpreter("5 in $", None);
+        let result = interpreter.interpret();
+        assert_eq!(result, Ok(ResType::Money(5.0, Currency::Dollar)));
+    }
+
+    #[test]
+    fn test_format_scientific() {
+        let mut interpreter = make_interpreter("10.0^20", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "1.0e20");
+
+        let mut interpreter = make_interpreter("1/10^9", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "1.0e-9");
+
+        // the integers stay exact
+        let mut interpreter = make_interpreter("10^20", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "100000000000000000000");
+
+        let mut interpreter = make_interpreter("1234.5 * 2", None);
+        assert_eq!(interpreter.interpret().unwrap().to_string(), "2469.0");
+        assert_eq!(ResType::Float(0.001).to_string(), "0.001");
+        assert_eq!(ResType::Float(-2.5e-7).to_string(), "-2.5e-7");
+
+        let options = FormatOptions { scientific_bounds: Some((-2, 3)), precision: Some(2), ..Default::default() };
+        assert_eq!(ResType::Float(12345.678).format(&options), "1.23e4");
+        assert_eq!(ResType::Float(123.456).format(&options), "123.46");
+        assert_eq!(ResType::Float(0.001).format(&options), "1.00e-3");
+    }
+
+    #[test]
+    fn test_format_precision() {
+        let mut interpreter = make_interpreter("1.0/3.0", None);
+        let result = interpreter.interpret().unwrap();
+
+        assert_eq!(result.format(&FormatOptions { precision: Some(2), ..Default::default() }), "0.33");
+        assert_eq!(result.format(&FormatOptions { precision: Some(4), ..Default::default() }), "0.3333");
+        assert_eq!(result.format(&FormatOptions { precision: Some(6), ..Default::default() }), "0.333333");
+        assert_eq!(result.format(&FormatOptions::default()), result.to_string());
+
+        let mut interpreter = make_interpreter("10€/3", None);
+        let result = interpreter.interpret().unwrap();
+        assert_eq!(result.format(&FormatOptions { precision: Some(4), ..Default::default() }), "3.3333 €");
+        assert_eq!(result.format(&FormatOptions::default()), "3.33 €");
+    }
+
+    #[test]
+    fn test_format_thousands_separator() {
+        let comma = FormatOptions { thousands_separator: Some(','), ..Default::default() };
+        let space = FormatOptions { thousands_separator: Some(' '), ..Default::default() };
+
+        let mut interpreter = make_interpreter("1000000", None);
+        let result = interpreter.interpret().unwrap();
+        assert_eq!(result.format(&comma), "1,000,000");
+        assert_eq!(result.format(&space), "1 000 000");
+        assert_eq!(result.format(&FormatOptions::default()), "1000000");
+
+        let mut interpreter = make_interpreter("1234.5€", None);
+        let result = interpreter.interpret().unwrap();
+        assert_eq!(result.format(&comma), "1,234.50 €");
+
+        assert_eq!(ResType::Int(-123456).format(&comma), "-123,456");
+        assert_eq!(ResType::Int(100).format(&comma), "100");
+        assert_eq!(ResType::Float(12345.6789).format(&comma), "12,345.6789");
+    }
+
+    #[test]
+    fn test_total() {
+        let rates = HashMap::from([((Currency::Euro, Currency::Dollar), 1.25)]);
+
+        let values = [ResType::Int(2), ResType::Float(0.5), ResType::Int(3)];
+        assert_eq!(total(&values, &rates), Ok(ResType::Float(5.5)));
+
+        let values = [ResType::Money(10.0, Currency::Euro), ResType::Money(5.0, Currency::Dollar)];
+        assert_eq!(total(&values, &rates), Ok(ResType::Money(14.0, Currency::Euro)));
+        assert_eq!(total(&values, &HashMap::new()), Err(Error::NoConversionRate));
+    }
+
+    #[test]
+    fn test_solve_without_rates() {
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+
+        let result = solve(String::from("10€ + 5$"), vars, &HashMap::new());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_solve_with_rates() {
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+        let rates = HashMap::from([((Currency::Euro, Currency::Dollar), 1.25)]);
+
+        let result = solve(String::from("10€ - 5$"), vars, &rates);
+        assert_eq!(result, Ok(ResType::Money(6.0, Currency::Euro)));
+    }
+
     #[test]
     fn test_handling_spaces() {
         let mut interpreter = make_interpreter("4€ b", None);
@@ -1126,7 +5742,21 @@ mod tests {
     }
 
     #[test]
-    #[ignore]
+    fn implicit_multiplication_parentheses() {
+        let mut interpreter = make_interpreter("2(3+4)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(14)));
+
+        let mut interpreter = make_interpreter("(1+1)(2+2)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(8)));
+
+        let mut interpreter = make_interpreter("3(2)", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(6)));
+
+        let mut interpreter = make_interpreter("2(3)! + 1", None);
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(13)));
+    }
+
+    #[test]
     fn implicit_multiplication2() {
         let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
 
@@ -1139,6 +5769,41 @@ mod tests {
         assert_eq!(result, Ok(ResType::Int(-24)));
     }
 
+    #[test]
+    fn implicit_multiplication_split_names() {
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+        vars.borrow_mut().insert(String::from("a"), ResType::Int(2));
+        vars.borrow_mut().insert(String::from("b"), ResType::Int(3));
+        vars.borrow_mut().insert(String::from("c"), ResType::Int(5));
+
+        let mut interpreter = make_interpreter("2abc", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(60)));
+
+        // a variable with the whole name wins
+        vars.borrow_mut().insert(String::from("ab"), ResType::Int(10));
+        let mut interpreter = make_interpreter("4ab", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(40)));
+
+        // the longest name first
+        let mut interpreter = make_interpreter("abc", Some(vars.clone()));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(50)));
+
+        // all the letters must be variables
+        let mut interpreter = make_interpreter("abx", Some(vars));
+        assert!(matches!(interpreter.interpret(), Err(Error::UndefinedVariable(_, _))));
+
+        // a long name that can't be split is undefined right away
+        let vars : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+        vars.borrow_mut().insert(String::from("a"), ResType::Int(1));
+        vars.borrow_mut().insert(String::from("aa"), ResType::Int(1));
+        let name = "a".repeat(200) + "b";
+        let mut interpreter = make_interpreter(&name, Some(vars.clone()));
+        assert!(matches!(interpreter.interpret(), Err(Error::UndefinedVariable(_, _))));
+
+        let mut interpreter = make_interpreter(&"a".repeat(201), Some(vars));
+        assert_eq!(interpreter.interpret(), Ok(ResType::Int(1)));
+    }
+
     #[test]
     #[ignore]
     fn implicit_multiplication3() {
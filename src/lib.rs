@@ -0,0 +1,41 @@
+//! The calculator behind Luca, usable without the GTK interface.
+//!
+//! [`solve`] evaluates one line of input. The variables assigned on a line
+//! are stored in the shared map, so the following lines can use them:
+//!
+//! ```
+//! use std::cell::RefCell;
+//! use std::collections::HashMap;
+//! use std::rc::Rc;
+//!
+//! let variables = Rc::new(RefCell::new(HashMap::new()));
+//! let rates = HashMap::new();
+//!
+//! luca::solve(String::from("price = 12€"), variables.clone(), &rates).unwrap();
+//! let total = luca::solve(String::from("3 * price"), variables, &rates);
+//! assert_eq!(total.unwrap().to_string(), "36.00 €");
+//! ```
+//!
+//! For more control, like adding conversion rates one by one,
+//! build an [`Interpreter`] from a [`Parser`] and a [`Lexer`]:
+//!
+//! ```
+//! use std::cell::RefCell;
+//! use std::collections::HashMap;
+//! use std::rc::Rc;
+//! use luca::{AngleMode, Currency, Interpreter, Lexer, Parser, ResType};
+//!
+//! let lexer = Lexer::new(String::from("10€ in $"));
+//! let parser = Parser::new(lexer).unwrap();
+//! let mut interpreter = Interpreter::new(parser, Rc::new(RefCell::new(HashMap::new())));
+//! interpreter.add_rate(Currency::Euro, Currency::Dollar, 1.25);
+//!
+//! assert_eq!(interpreter.interpret(), Ok(ResType::Money(12.5, Currency::Dollar)));
+//! ```
+
+mod interpreter;
+
+pub use interpreter::{
+    is_line_variable, solve, solve_document, solve_lines, solve_statement, solve_typed, total, variables_listing, AngleMode, Currency, Definition, Error, FormatOptions, Interpreter, Lexer, LineState, MoneyRounding, NegativeMoney, Parser, ResType,
+    Solution, StatementKind, SymbolPlacement, Unit
+};
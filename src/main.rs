@@ -1,26 +1,78 @@
-use gtk::{gdk, glib, glib::clone};
-use gtk::prelude::{GtkWindowExt, OrientableExt, WidgetExt};
+use gtk::{gdk, gio, glib, glib::clone};
+use gtk::prelude::{ButtonExt, FileExt, GtkWindowExt, OrientableExt, ToggleButtonExt, WidgetExt};
 use relm4::{gtk, Component, ComponentController, ComponentParts, ComponentSender, Controller, RelmApp, SimpleComponent};
+use relm4::actions::{AccelsPlus, RelmAction, RelmActionGroup};
 use granite::prelude::SettingsExt;
+use gio::prelude::SettingsExt as _;
+use luca::AngleMode;
+use std::fs;
+use std::path::PathBuf;
 
 mod input_pane;
-use input_pane::{LucaInput, MsgInput};
+use input_pane::{InputMsg, LineResult, LucaInput, MsgInput};
 
 mod result_pane;
 use result_pane::{ResultView, ResultMsg};
 
-mod interpreter;
+mod export;
+mod cli;
+mod session;
 
 
+const APP_ID: &str = "io.github.falafel.luca";
+
+relm4::new_action_group!(WindowActionGroup, "win");
+relm4::new_stateless_action!(SaveAction, WindowActionGroup, "save");
+relm4::new_stateless_action!(OpenAction, WindowActionGroup, "open");
+relm4::new_stateless_action!(ExportCsvAction, WindowActionGroup, "export-csv");
+relm4::new_stateless_action!(CopyMarkdownAction, WindowActionGroup, "copy-markdown");
+relm4::new_stateless_action!(ClearAction, WindowActionGroup, "clear");
+relm4::new_stateful_action!(LineNumbersAction, WindowActionGroup, "line-numbers", (), bool);
+relm4::new_stateless_action!(ZoomInAction, WindowActionGroup, "zoom-in");
+relm4::new_stateless_action!(ZoomOutAction, WindowActionGroup, "zoom-out");
+relm4::new_stateless_action!(ZoomResetAction, WindowActionGroup, "zoom-reset");
+
 // Application model
 #[derive(Debug)]
 enum AppMsg {
-    TextChanged(String)
+    TextChanged(Vec<LineResult>),
+    Save,
+    SaveAs(PathBuf),
+    Open,
+    OpenFile(PathBuf),
+    ExportCsv,
+    ExportCsvTo(PathBuf),
+    CopyMarkdown,
+    /// Clear the document, after asking the user if it isn't empty
+    Clear,
+    /// Clear the document and its variables, without asking
+    ClearConfirmed,
+    Cleared,
+    Loaded(Vec<LineResult>),
+    /// The results changed, but not the document
+    Recomputed(Vec<LineResult>),
+    ShowLineNumbers(bool),
+    /// Solve the trigonometric functions in degrees instead of radians
+    UseDegrees(bool),
+    ZoomIn,
+    ZoomOut,
+    ZoomReset
 }
 
 struct AppModel {
     input: Controller<LucaInput>,
-    result: Controller<ResultView>
+    result: Controller<ResultView>,
+    window: gtk::Window,
+    /// File where the document is saved
+    file: Option<PathBuf>,
+    /// The document has been modified since it was saved
+    dirty: bool,
+    /// The result of each line of the document
+    results: Vec<LineResult>,
+    /// Scale of the text of both panes, 1.0 is the default size
+    zoom: f64,
+    css_provider: gtk::CssProvider,
+    settings: Option<gio::Settings>
 }
 
 #[relm4::component]
@@ -39,9 +91,11 @@ impl SimpleComponent for AppModel {
             set_default_width: 600,
             set_default_height: 400,
             set_width_request: 370,
-            set_title: Some(""),
+            #[watch]
+            set_title: Some(&model.title()),
             set_titlebar: Some(&gtk::Grid::new()), // set an emply headerbar
 
+            #[name = "paned"]
             gtk::Paned {
                 set_orientation: gtk::Orientation::Horizontal,
 
@@ -55,6 +109,7 @@ impl SimpleComponent for AppModel {
                         add_css_class: "view",
                     },
 
+                    #[name = "input_scroll"]
                     gtk::ScrolledWindow {
                         set_vexpand: true,
                         add_css_class: "view",
@@ -76,9 +131,18 @@ impl SimpleComponent for AppModel {
                             pack_end = &gtk::WindowControls{
                                 set_side: gtk::PackType::End,
                             },
+                            pack_start = &gtk::ToggleButton {
+                                set_label: "DEG",
+                                set_tooltip_text: Some("Angles in degrees"),
+                                add_css_class: "flat",
+                                connect_toggled[sender] => move |button| {
+                                    sender.input(AppMsg::UseDegrees(button.is_active()));
+                                }
+                            },
                             add_css_class: "sidebar"
                         },
                         
+                        #[name = "result_scroll"]
                         gtk::ScrolledWindow {
                             set_vexpand: true,
                             add_css_class: "view",
@@ -97,14 +161,26 @@ impl SimpleComponent for AppModel {
         window: Self::Root,
         sender: ComponentSender<Self>,
     ) -> relm4::ComponentParts<Self> {
-        load_css();
+        let settings = settings();
+        let zoom = settings.as_ref().map_or(1.0, |settings| settings.double("zoom"));
+        let css_provider = load_css(zoom);
+
         let text_input: Controller<LucaInput> = 
             LucaInput::builder()
                 .launch(String::from(""))
                 .forward(sender.input_sender(), |msg| match msg {
-                    MsgInput::TextChanged(new_text) => {AppMsg::TextChanged(new_text)}
+                    MsgInput::TextChanged(results) => {AppMsg::TextChanged(results)},
+                    MsgInput::Loaded(results) => {AppMsg::Loaded(results)},
+                    MsgInput::Recomputed(results) => {AppMsg::Recomputed(results)},
+                    MsgInput::Cleared => {AppMsg::Cleared}
                 });
 
+        // reopen with the document of the last session
+        let text = session::load(&session::path());
+        if !text.is_empty() {
+            text_input.emit(InputMsg::Load(text));
+        }
+
         let result_view: Controller<ResultView> = 
             ResultView::builder()
                 .launch(String::from(""))
@@ -112,30 +188,298 @@ impl SimpleComponent for AppModel {
 
         let model = AppModel {
             input: text_input,
-            result: result_view
+            result: result_view,
+            window: window.clone(),
+            file: None,
+            dirty: false,
+            results: Vec::new(),
+            zoom,
+            css_provider,
+            settings
         };
         let widgets = view_output!();
 
+        // the lines don't wrap, each result is on the row of its line:
+        // both panes scroll together to keep them side by side
+        widgets.result_scroll.set_vadjustment(Some(&widgets.input_scroll.vadjustment()));
+
+        // restore the size of the window, and save it when it's closed
+        if let Some(settings) = &model.settings {
+            let (width, height) = (settings.int("window-width"), settings.int("window-height"));
+            if width > 0 && height > 0 {
+                window.set_default_size(width, height);
+            }
+            let position = settings.int("paned-position");
+            if position > 0 {
+                widgets.paned.set_position(position);
+            }
+
+            window.connect_close_request(clone!(@strong settings, @weak widgets.paned as paned => @default-return glib::Propagation::Proceed, move |window| {
+                let (width, height) = window.default_size();
+                _ = settings.set_int("window-width", width);
+                _ = settings.set_int("window-height", height);
+                _ = settings.set_int("paned-position", paned.position());
+                glib::Propagation::Proceed
+            }));
+        }
+
+        // keyboard shortcuts
+        let app = relm4::main_application();
+        app.set_accelerators_for_action::<SaveAction>(&["<primary>s"]);
+        app.set_accelerators_for_action::<OpenAction>(&["<primary>o"]);
+        app.set_accelerators_for_action::<ExportCsvAction>(&["<primary><shift>e"]);
+        app.set_accelerators_for_action::<CopyMarkdownAction>(&["<primary><shift>m"]);
+        app.set_accelerators_for_action::<ClearAction>(&["<primary>l"]);
+        app.set_accelerators_for_action::<LineNumbersAction>(&["<primary><shift>n"]);
+        app.set_accelerators_for_action::<ZoomInAction>(&["<primary>plus", "<primary>equal", "<primary>KP_Add"]);
+        app.set_accelerators_for_action::<ZoomOutAction>(&["<primary>minus", "<primary>KP_Subtract"]);
+        app.set_accelerators_for_action::<ZoomResetAction>(&["<primary>0", "<primary>KP_0"]);
+
+        let save_action: RelmAction<SaveAction> = RelmAction::new_stateless(
+            clone!(@strong sender => move |_| sender.input(AppMsg::Save))
+        );
+        let open_action: RelmAction<OpenAction> = RelmAction::new_stateless(
+            clone!(@strong sender => move |_| sender.input(AppMsg::Open))
+        );
+        let export_csv_action: RelmAction<ExportCsvAction> = RelmAction::new_stateless(
+            clone!(@strong sender => move |_| sender.input(AppMsg::ExportCsv))
+        );
+        let copy_markdown_action: RelmAction<CopyMarkdownAction> = RelmAction::new_stateless(
+            clone!(@strong sender => move |_| sender.input(AppMsg::CopyMarkdown))
+        );
+        let clear_action: RelmAction<ClearAction> = RelmAction::new_stateless(
+            clone!(@strong sender => move |_| sender.input(AppMsg::Clear))
+        );
+        // the line numbers are hidden by default
+        let line_numbers_action: RelmAction<LineNumbersAction> = RelmAction::new_stateful(&false,
+            clone!(@strong sender => move |_, show: &mut bool| {
+                *show = !*show;
+                sender.input(AppMsg::ShowLineNumbers(*show));
+            })
+        );
+        let zoom_in_action: RelmAction<ZoomInAction> = RelmAction::new_stateless(
+            clone!(@strong sender => move |_| sender.input(AppMsg::ZoomIn))
+        );
+        let zoom_out_action: RelmAction<ZoomOutAction> = RelmAction::new_stateless(
+            clone!(@strong sender => move |_| sender.input(AppMsg::ZoomOut))
+        );
+        let zoom_reset_action: RelmAction<ZoomResetAction> = RelmAction::new_stateless(
+            clone!(@strong sender => move |_| sender.input(AppMsg::ZoomReset))
+        );
+
+        let mut actions = RelmActionGroup::<WindowActionGroup>::new();
+        actions.add_action(save_action);
+        actions.add_action(open_action);
+        actions.add_action(export_csv_action);
+        actions.add_action(copy_markdown_action);
+        actions.add_action(clear_action);
+        actions.add_action(line_numbers_action);
+        actions.add_action(zoom_in_action);
+        actions.add_action(zoom_out_action);
+        actions.add_action(zoom_reset_action);
+        actions.register_for_widget(&widgets.main_window);
+
         ComponentParts { model, widgets }
     }
 
-    fn update(&mut self, message: Self::Input, _sender: ComponentSender<Self>) {
+    fn update(&mut self, message: Self::Input, sender: ComponentSender<Self>) {
         match message {
-            AppMsg::TextChanged(new_text) => {
-                self.result.emit(ResultMsg::TextChanged(new_text))
-            }
+            AppMsg::TextChanged(results) => {
+                self.dirty = true;
+                // the changes arrive once the user stops typing, there's no need to wait more before saving
+                _ = session::save(&session::path(), &self.input.model().text());
+                self.results = results.clone();
+                self.result.emit(ResultMsg::TextChanged(results))
+            },
+            AppMsg::Save => {
+                match &self.file {
+                    Some(path) => self.save(path.clone()),
+                    None => {
+                        // ask where to save a new document
+                        let dialog = gtk::FileDialog::builder()
+                            .title("Save")
+                            .initial_name("Untitled.luca")
+                            .modal(true)
+                            .build();
+
+                        dialog.save(Some(&self.window), gio::Cancellable::NONE, clone!(@strong sender => move |file| {
+                            if let Some(path) = file.ok().and_then(|file| file.path()) {
+                                sender.input(AppMsg::SaveAs(path));
+                            }
+                        }));
+                    }
+                }
+            },
+            AppMsg::SaveAs(path) => self.save(path),
+            AppMsg::Open => {
+                let dialog = gtk::FileDialog::builder()
+                    .title("Open")
+                    .modal(true)
+                    .build();
+
+                dialog.open(Some(&self.window), gio::Cancellable::NONE, clone!(@strong sender => move |file| {
+                    if let Some(path) = file.ok().and_then(|file| file.path()) {
+                        sender.input(AppMsg::OpenFile(path));
+                    }
+                }));
+            },
+            AppMsg::OpenFile(path) => {
+                match fs::read_to_string(&path) {
+                    Ok(text) => {
+                        self.file = Some(path);
+                        self.input.emit(InputMsg::Load(text));
+                    },
+                    Err(error) => self.show_error(&format!("Could not open {}", path.display()), &error.to_string())
+                }
+            },
+            AppMsg::ExportCsv => {
+                let name = self.file.as_ref()
+                    .and_then(|path| path.file_stem())
+                    .map_or(String::from("Untitled"), |name| name.to_string_lossy().to_string());
+
+                let dialog = gtk::FileDialog::builder()
+                    .title("Export as CSV")
+                    .initial_name(format!("{}.csv", name))
+                    .modal(true)
+                    .build();
+
+                dialog.save(Some(&self.window), gio::Cancellable::NONE, clone!(@strong sender => move |file| {
+                    if let Some(path) = file.ok().and_then(|file| file.path()) {
+                        sender.input(AppMsg::ExportCsvTo(path));
+                    }
+                }));
+            },
+            AppMsg::ExportCsvTo(path) => {
+                let csv = export::to_csv(&self.input.model().text(), &self.results);
+                if let Err(error) = fs::write(&path, csv) {
+                    self.show_error(&format!("Could not export {}", path.display()), &error.to_string());
+                }
+            },
+            AppMsg::CopyMarkdown => {
+                let markdown = export::to_markdown(&self.input.model().text(), &self.results);
+                self.window.clipboard().set_text(&markdown);
+            },
+            AppMsg::Loaded(results) => {
+                self.dirty = false;
+                _ = session::save(&session::path(), &self.input.model().text());
+                self.results = results.clone();
+                self.result.emit(ResultMsg::TextChanged(results))
+            },
+            AppMsg::Clear => {
+                if self.input.model().text().is_empty() {
+                    sender.input(AppMsg::ClearConfirmed);
+                    return;
+                }
+
+                let dialog = gtk::AlertDialog::builder()
+                    .message("Clear the document?")
+                    .detail("The lines and their variables will be removed.")
+                    .buttons(["Cancel", "Clear"])
+                    .cancel_button(0)
+                    .default_button(1)
+                    .modal(true)
+                    .build();
+
+                dialog.choose(Some(&self.window), gio::Cancellable::NONE, clone!(@strong sender => move |button| {
+                    if button == Ok(1) {
+                        sender.input(AppMsg::ClearConfirmed);
+                    }
+                }));
+            },
+            AppMsg::ClearConfirmed => self.input.emit(InputMsg::Clear),
+            AppMsg::Cleared => {
+                // a blank sheet, like when Luca is started for the first time
+                self.file = None;
+                self.dirty = false;
+                self.results.clear();
+                _ = session::save(&session::path(), "");
+                self.result.emit(ResultMsg::TextChanged(Vec::new()))
+            },
+            AppMsg::Recomputed(results) => {
+                self.results = results.clone();
+                self.result.emit(ResultMsg::TextChanged(results))
+            },
+            AppMsg::UseDegrees(degrees) => {
+                let angle_mode = if degrees { AngleMode::Degrees } else { AngleMode::Radians };
+                self.input.emit(InputMsg::SetAngleMode(angle_mode))
+            },
+            AppMsg::ShowLineNumbers(show) => {
+                self.input.emit(InputMsg::ShowLineNumbers(show))
+            },
+            AppMsg::ZoomIn => self.set_zoom(self.zoom + 0.1),
+            AppMsg::ZoomOut => self.set_zoom(self.zoom - 0.1),
+            AppMsg::ZoomReset => self.set_zoom(1.0)
+        }
+    }
+}
+
+impl AppModel {
+    /// The name of the document, with an asterisk if it has unsaved changes
+    fn title(&self) -> String {
+        let name = self.file.as_ref()
+            .and_then(|path| path.file_name())
+            .map_or(String::from("Untitled"), |name| name.to_string_lossy().to_string());
+
+        if self.dirty {
+            format!("{}*", name)
+        } else {
+            name
+        }
+    }
+
+    /// Write the document to `path`, the following saves will go to the same file
+    fn save(&mut self, path: PathBuf) {
+        match fs::write(&path, self.input.model().text()) {
+            Ok(()) => {
+                self.file = Some(path);
+                self.dirty = false;
+            },
+            Err(error) => self.show_error(&format!("Could not save {}", path.display()), &error.to_string())
+        }
+    }
+
+    /// Scale the text of the input and the result together, so their lines stay aligned
+    fn set_zoom(&mut self, zoom: f64) {
+        self.zoom = zoom.clamp(0.5, 3.0);
+        self.css_provider.load_from_data(&css(self.zoom));
+
+        if let Some(settings) = &self.settings {
+            _ = settings.set_double("zoom", self.zoom);
         }
     }
+
+    fn show_error(&self, message: &str, detail: &str) {
+        gtk::AlertDialog::builder()
+            .message(message)
+            .detail(detail)
+            .modal(true)
+            .build()
+            .show(Some(&self.window));
+    }
+}
+
+/// The settings of the application, if its schema is installed
+fn settings() -> Option<gio::Settings> {
+    let schema = gio::SettingsSchemaSource::default()?.lookup(APP_ID, true)?;
+    Some(gio::Settings::new_full(&schema, None::<&gio::SettingsBackend>, None))
+}
+
+/// Our custom CSS, with the size of the text scaled by `zoom`
+fn css(zoom: f64) -> String {
+    format!(
+        "{}\n.text {{ font-size: {}px; line-height: {}px; }}",
+        include_str!("../data/style.css"), 18.0 * zoom, 30.0 * zoom
+    )
 }
 
 // from https://jamesbenner.hashnode.dev/how-to-style-your-gtk4-rust-app-with-css
-fn load_css() {
+fn load_css(zoom: f64) -> gtk::CssProvider {
     let display = gdk::Display::default().expect("Could not get default display.");
     let provider = gtk::CssProvider::new();
     let priority = gtk::STYLE_PROVIDER_PRIORITY_APPLICATION;
 
     // load our custom CSS
-    provider.load_from_data(include_str!("../data/style.css"));
+    provider.load_from_data(&css(zoom));
     gtk::style_context_add_provider_for_display(&display, &provider, priority);
 
 
@@ -162,10 +506,17 @@ fn load_css() {
             );
         }
     }
+
+    provider
 }
 
 fn main() {
+    // the command line mode doesn't need the window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
+    }
 
-    let app = RelmApp::new("io.github.falafel.luca");
+    let app = RelmApp::new(APP_ID);
     app.run::<AppModel>(());
 }
\ No newline at end of file
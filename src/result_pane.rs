@@ -1,16 +1,19 @@
 use gtk::prelude::{WidgetExt, TextBufferExt, TextViewExt};
 use relm4::{gtk, ComponentParts, ComponentSender, SimpleComponent};
 
+use crate::input_pane::{highlight_errors, LineResult};
+
 
 // Input component
 
 pub struct ResultView {
-    text_buffer: gtk::TextBuffer
+    text_buffer: gtk::TextBuffer,
+    error_tag: gtk::TextTag
 }
 
 #[derive(Debug)]
 pub enum ResultMsg {
-    TextChanged(String)
+    TextChanged(Vec<LineResult>)
 }
 
 #[relm4::component(pub)]
@@ -23,6 +26,8 @@ impl SimpleComponent for ResultView {
         gtk::TextView {
             set_margin_start: 20,
             set_editable: false,
+            // a result is on the row of its line, see the input
+            set_wrap_mode: gtk::WrapMode::None,
             set_buffer: Some(&model.text_buffer)
         },
     }
@@ -35,15 +40,24 @@ impl SimpleComponent for ResultView {
         let text_buffer = gtk::TextBuffer::new(None);
         text_buffer.set_text(&text);
 
-        let model = ResultView {text_buffer};
+        // the results of the lines that couldn't be computed are in red
+        let error_tag = gtk::TextTag::builder()
+            .name("error")
+            .foreground("#c6262e")
+            .build();
+        text_buffer.tag_table().add(&error_tag);
+
+        let model = ResultView {text_buffer, error_tag};
         let widgets = view_output!();
         ComponentParts {model, widgets}
     }
 
     fn update(&mut self, msg: Self::Input, _sender: ComponentSender<Self>) {
         match msg {
-            ResultMsg::TextChanged(text) => {
-                self.text_buffer.set_text(&text);
+            ResultMsg::TextChanged(results) => {
+                let text: Vec<&str> = results.iter().map(LineResult::text).collect();
+                self.text_buffer.set_text(&text.join("\n"));
+                highlight_errors(&self.text_buffer, &self.error_tag, &results);
             }
         }
     }
@@ -0,0 +1,48 @@
+use relm4::gtk::glib;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// The document of the last session, saved while typing so Luca reopens with it
+
+/// The file keeping the document between the sessions, in the cache directory of the user
+pub fn path() -> PathBuf {
+    glib::user_cache_dir().join("luca").join("session.txt")
+}
+
+/// Save the `text` of the document to the session file at `path`
+pub fn save(path: &Path, text: &str) -> io::Result<()> {
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    fs::write(path, text)
+}
+
+/// The document saved at `path`. Empty if there's none yet, or if the file can't be read.
+pub fn load(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_round_trip() {
+        let directory = std::env::temp_dir().join(format!("luca-session-{}", std::process::id()));
+        let path = directory.join("cache").join("session.txt");
+
+        // nothing saved yet
+        assert_eq!(load(&path), "");
+
+        let text = "price = 12€\n\n# the total\nprice * 3";
+        save(&path, text).unwrap();
+        assert_eq!(load(&path), text);
+
+        // a file that isn't text is ignored
+        fs::write(&path, [0xff, 0xfe, 0x00]).unwrap();
+        assert_eq!(load(&path), "");
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,104 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+#[test]
+fn cli_eval() {
+    let output = Command::new(env!("CARGO_BIN_EXE_luca"))
+        .args(["--eval", "2+2"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "4\n");
+}
+
+#[test]
+fn cli_eval_error() {
+    let output = Command::new(env!("CARGO_BIN_EXE_luca"))
+        .args(["--eval", "1 / 0"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "Division by zero\n");
+}
+
+/// Run luca with the `args`, and `input` on its standard input
+fn run_with_input(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_luca"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn cli_stdin() {
+    let output = run_with_input(&["--stdin"], "price = 4\nprice * 3\n\n# a comment\nline2 + 1\n");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "4\n12\n13\n");
+}
+
+#[test]
+fn cli_stdin_vars() {
+    let output = run_with_input(&["--stdin"], "a = 1\nb = 2\nvars\n");
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n2\na = 1, b = 2\n");
+}
+
+#[test]
+fn cli_stdin_errors() {
+    let output = run_with_input(&["--stdin"], "2 + * 3\n5\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "5\n");
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "line 1: Invalid syntax at column 5\n");
+
+    // a statement continued until the end of the input
+    let output = run_with_input(&["--stdin"], "2 +\n5 *\n");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "line 2: Invalid syntax at column 8\n");
+
+    let output = run_with_input(&["--stdin", "--ignore-errors"], "2 + * 3\n5\n");
+    assert!(output.status.success());
+}
+
+#[test]
+fn cli_json() {
+    let output = run_with_input(&["--stdin", "--json"], "2 + 3\n1.5 * 2.5\n10€ + 5€\n\n3 / 0\n");
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8_lossy(&output.stdout), concat!(
+        "[\n",
+        "  {\"input\": \"2 + 3\", \"value\": 5, \"type\": \"Int\", \"error\": null},\n",
+        "  {\"input\": \"1.5 * 2.5\", \"value\": 3.75, \"type\": \"Float\", \"error\": null},\n",
+        "  {\"input\": \"10€ + 5€\", \"value\": 15, \"type\": \"Money\", \"currency\": \"EUR\", \"error\": null},\n",
+        "  {\"input\": \"3 / 0\", \"value\": null, \"type\": null, \"error\": \"Division by zero\"}\n",
+        "]\n"
+    ));
+
+    // the input of a continued statement is on all its lines
+    let output = run_with_input(&["--stdin", "--json"], "price = 12 +\n  8\n");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), concat!(
+        "[\n",
+        "  {\"input\": \"price = 12 + 8\", \"value\": 20, \"type\": \"Int\", \"error\": null}\n",
+        "]\n"
+    ));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_luca"))
+        .args(["--eval", "\"quoted\"", "--json"])
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), concat!(
+        "[\n",
+        "  {\"input\": \"\\\"quoted\\\"\", \"value\": null, \"type\": null, \"error\": \"Invalid syntax at column 1\"}\n",
+        "]\n"
+    ));
+}
@@ -0,0 +1,236 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use luca::{solve, solve_document, solve_lines, solve_typed, total, Currency, Error, ResType};
+
+#[test]
+fn solve_expression() {
+    let variables : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+    let result = solve(String::from("2 + 7 * 4"), variables, &HashMap::new());
+    assert_eq!(result, Ok(ResType::Int(30)));
+}
+
+#[test]
+fn solve_variables_across_lines() {
+    let variables : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+    let rates = HashMap::new();
+
+    _ = solve(String::from("adulte = 12€"), variables.clone(), &rates);
+    let result = solve(String::from("2 adultes"), variables, &rates);
+    assert_eq!(result.unwrap().to_string(), "24.00 €");
+}
+
+#[test]
+fn solve_invalid_syntax() {
+    let variables : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+    let result = solve(String::from("10 *"), variables, &HashMap::new());
+    assert!(result.is_err());
+}
+
+#[test]
+fn solve_previous_answer() {
+    let variables : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+    let rates = HashMap::new();
+
+    // the input pane stores each result under `ans` for the next line
+    let result = solve(String::from("5+5"), variables.clone(), &rates).unwrap();
+    variables.borrow_mut().insert(String::from("ans"), result);
+
+    let result = solve(String::from("ans*2"), variables, &rates);
+    assert_eq!(result, Ok(ResType::Int(20)));
+}
+
+#[test]
+fn solve_line_references() {
+    let variables : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+    let rates = HashMap::new();
+
+    // the input pane stores the result of line N under `lineN`
+    for (number, line) in ["3 * 4", "8 - 2"].iter().enumerate() {
+        let result = solve(line.to_string(), variables.clone(), &rates).unwrap();
+        variables.borrow_mut().insert(format!("line{}", number + 1), result);
+    }
+
+    let result = solve(String::from("line1 + line2"), variables.clone(), &rates);
+    assert_eq!(result, Ok(ResType::Int(18)));
+
+    // the line 4 hasn't been computed
+    assert!(solve(String::from("line4"), variables, &rates).is_err());
+}
+
+#[test]
+fn solve_total() {
+    let variables : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+    let rates = HashMap::new();
+
+    // the input pane stores the sum of the previous lines under `total`
+    let mut results = Vec::new();
+    for line in ["10€", "20€"] {
+        results.push(solve(line.to_string(), variables.clone(), &rates).unwrap());
+    }
+    variables.borrow_mut().insert(String::from("total"), total(&results, &rates).unwrap());
+
+    let result = solve(String::from("total"), variables, &rates);
+    assert_eq!(result.unwrap().to_string(), "30.00 €");
+}
+
+#[test]
+fn solve_lines_vars() {
+    let variables : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+    let results = solve_lines("vars\na = 1\nb = 2 * a\n3 + b\nvars  # audit\nans", variables, &HashMap::new());
+
+    let listing = |line: &Option<Result<luca::Solution, String>>| line.clone().unwrap().unwrap().value.to_string();
+    assert_eq!(listing(&results[0]), "No variables");
+    // the lines variables like `ans` aren't listed, and `vars` isn't the next `ans`
+    assert_eq!(listing(&results[4]), "a = 1, b = 2");
+    assert_eq!(listing(&results[5]), "5");
+}
+
+#[test]
+fn solve_document_vars() {
+    let results = solve_document("a=1\nb=2\nvars\ntotal");
+
+    assert_eq!(results[2], Ok(ResType::Variables(vec![(String::from("a"), ResType::Int(1)), (String::from("b"), ResType::Int(2))])));
+    assert_eq!(results[3], Ok(ResType::Int(3)));
+}
+
+#[test]
+fn solve_document_assignments() {
+    let results = solve_document("a = 2\nb = a * 3\n\nb + ans");
+
+    assert_eq!(results, vec![
+        Ok(ResType::Int(2)),
+        Ok(ResType::Int(6)),
+        Err(Error::InvalidSyntax(0)),
+        Ok(ResType::Int(12))
+    ]);
+}
+
+#[test]
+fn solve_document_error_line() {
+    let results = solve_document("4 * 5\n3 + * 2\nline1 / 2");
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0], Ok(ResType::Int(20)));
+    assert!(results[1].is_err());
+    assert_eq!(results[2], Ok(ResType::Int(10)));
+}
+
+#[test]
+fn solve_document_trailing_operator() {
+    let results = solve_document("price = 12 +\n    8\nprice * 2 -\n\n# the discount\n5");
+
+    assert_eq!(results, vec![
+        Err(Error::InvalidSyntax(0)),
+        Ok(ResType::Int(20)),
+        Err(Error::InvalidSyntax(0)),
+        Err(Error::InvalidSyntax(0)),
+        Err(Error::InvalidSyntax(0)),
+        Ok(ResType::Int(35))
+    ]);
+}
+
+#[test]
+fn solve_document_continued_comment() {
+    // the parenthesis and the operator of a comment don't continue the statement
+    let results = solve_document("2 + 3 # see (a\n4\n5 # minus -\n4");
+    assert_eq!(results, vec![Ok(ResType::Int(5)), Ok(ResType::Int(4)), Ok(ResType::Int(5)), Ok(ResType::Int(4))]);
+
+    // a statement continued after a comment
+    let results = solve_document("2 + # the tip\n4");
+    assert_eq!(results, vec![Err(Error::InvalidSyntax(0)), Ok(ResType::Int(6))]);
+}
+
+#[test]
+fn solve_document_circular_reference() {
+    let results = solve_document("a = b + 1\nb = a + 1\nb = 4\na * 2");
+
+    assert_eq!(results, vec![
+        Err(Error::UndefinedVariable(String::from("b"), None)),
+        Err(Error::CircularReference),
+        Ok(ResType::Int(4)),
+        // computed once `b` is defined
+        Ok(ResType::Int(10))
+    ]);
+}
+
+#[test]
+fn solve_document_variable_suffix() {
+    // the variables of the previous lines win over the units and the imaginary `i`
+    let results = solve_document("h = 3\n2h\ns = 5\n3s\ni = 5\n2i\nm = 2\n4m");
+    assert_eq!(results[1], Ok(ResType::Int(6)));
+    assert_eq!(results[3], Ok(ResType::Int(15)));
+    assert_eq!(results[5], Ok(ResType::Int(10)));
+    assert_eq!(results[7], Ok(ResType::Int(8)));
+
+    // without a variable, they are units
+    let results = solve_document("2h\n3s");
+    assert_eq!(results, vec![Ok(ResType::Duration(7200.0)), Ok(ResType::Duration(3.0))]);
+}
+
+#[test]
+fn solve_document_total() {
+    // the lines with the total aren't added to the next total
+    let results = solve_document("10€\n20€\ntotal\n5€\ntotal");
+    assert_eq!(results[2], Ok(ResType::Money(30.0, Currency::Euro)));
+    assert_eq!(results[4], Ok(ResType::Money(35.0, Currency::Euro)));
+
+    let results = solve_document("10\nx = total * 2\n5\ntotal");
+    assert_eq!(results[3], Ok(ResType::Int(15)));
+}
+
+#[test]
+fn solve_document_total_without_rate() {
+    let results = solve_document("10€\n5£\ntotal\n2 * total");
+    assert_eq!(results[2], Err(Error::NoConversionRate));
+    assert_eq!(results[3], Err(Error::NoConversionRate));
+}
+
+#[test]
+fn solve_document_user_total() {
+    // the variable of the user isn't replaced by the sum of the lines
+    let results = solve_document("total = 100\n5\ntotal + 1\ntotal += 2\ntotal");
+    assert_eq!(results[2], Ok(ResType::Int(101)));
+    assert_eq!(results[4], Ok(ResType::Int(102)));
+}
+
+#[test]
+fn solve_document_open_parenthesis() {
+    let results = solve_document("max(3,\n  10, 7)\n(1 + 2\n * 2) + line2");
+
+    assert_eq!(results[1], Ok(ResType::Int(10)));
+    assert_eq!(results[2], Err(Error::InvalidSyntax(0)));
+    assert_eq!(results[3], Ok(ResType::Int(15)));
+
+    // the error position is in the last line of the statement
+    let results = solve_document("(1 +\n 2 3)");
+    assert_eq!(results[1], Err(Error::InvalidSyntax(3)));
+
+    // a statement can't wait for a line after the end of the document
+    let results = solve_document("1\n2 *\n\n");
+    assert_eq!(results[1], Err(Error::InvalidSyntax(3)));
+}
+
+#[test]
+fn solve_typed_values() {
+    let variables : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+    let rates = HashMap::new();
+
+    assert_eq!(solve_typed(String::from("2 + 3"), variables.clone(), &rates), Ok(ResType::Int(5)));
+    assert_eq!(solve_typed(String::from("1.5 * 2.5"), variables.clone(), &rates), Ok(ResType::Float(3.75)));
+    assert_eq!(solve_typed(String::from("10€ + 5€"), variables.clone(), &rates), Ok(ResType::Money(15.0, Currency::Euro)));
+    assert_eq!(solve_typed(String::from("3 < 5"), variables, &rates), Ok(ResType::Bool(true)));
+}
+
+#[test]
+fn solve_typed_errors() {
+    let variables : Rc<RefCell<HashMap<String, ResType>>> = Rc::new(RefCell::new(HashMap::new()));
+    let rates = HashMap::new();
+
+    assert_eq!(solve_typed(String::from("1 / 0"), variables.clone(), &rates), Err(Error::DivisonByZero));
+    assert_eq!(solve_typed(String::from("unknown + 1"), variables.clone(), &rates), Err(Error::UndefinedVariable(String::from("unknown"), None)));
+    assert_eq!(solve_typed(String::from("5€ in $"), variables.clone(), &rates), Err(Error::NoConversionRate));
+    // the position counts the indentation
+    assert_eq!(solve_typed(String::from("  2 * * 3"), variables, &rates), Err(Error::InvalidSyntax(6)));
+}